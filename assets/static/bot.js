@@ -19,6 +19,7 @@ async function useBot(chess) {
             "Authorization": `Bearer ${token}`
         },
         body: JSON.stringify({
+            "game_id": gameId,
             "state_repr": {
                 "wqueen": wqueen,
                 "bqueen": bqueen,
@@ -31,5 +32,62 @@ async function useBot(chess) {
         throw new Error("Failed to get bot move");
     }
     let data = await response.json();
+    showInfo(data.info);
     return [data.move_made % 8, Math.floor(data.move_made / 8)];
+}
+
+function showInfo(info) {
+    let parts = [];
+    if (info.score !== null) {
+        let score = (info.score >= 0 ? "+" : "") + info.score.toFixed(2);
+        parts.push("score " + score + (info.proven ? " (proven)" : ""));
+    }
+    if (info.depth !== null) {
+        parts.push("depth " + info.depth);
+    }
+    if (info.nodes > 0) {
+        parts.push(info.nodes + " nodes");
+    }
+    parts.push((info.time_ms / 1000).toFixed(3) + "s");
+    let text = parts.join(", ");
+    if (info.pv.length > 1) {
+        text += "\npv " + info.pv.join(" ");
+    }
+    document.getElementById("bot-info").innerText = text;
+}
+
+async function useStart(spec) {
+    let url = "/start";
+    if (spec) {
+        url += "?spec=" + encodeURIComponent(spec);
+    }
+    let response = await fetch(url, {
+        headers: {
+            "Authorization": `Bearer ${token}`
+        }
+    });
+    if (!response.ok) {
+        throw new Error(await response.text());
+    }
+    return await response.json();
+}
+
+async function useRecord(start, moves) {
+    let response = await fetch("/record", {
+        method: "POST",
+        headers: {
+            "Content-Type": "application/json",
+            "Authorization": `Bearer ${token}`
+        },
+        body: JSON.stringify({
+            "game_id": gameId,
+            "start": start,
+            "moves": moves
+        })
+    });
+    if (!response.ok) {
+        throw new Error("Failed to save game record");
+    }
+    let data = await response.json();
+    return data.record;
 }
\ No newline at end of file
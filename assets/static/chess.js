@@ -57,6 +57,16 @@ class Chess {
         this.wFirst = true;
     }
 
+    setState(stateRepr) {
+        this.wPos = [stateRepr.wqueen % 8, Math.floor(stateRepr.wqueen / 8)];
+        this.bPos = [stateRepr.bqueen % 8, Math.floor(stateRepr.bqueen / 8)];
+        let blocks = BigInt(stateRepr.blocks);
+        for (let i = 0; i < 64; i++) {
+            this.blocks[`${i % 8},${Math.floor(i / 8)}`] = ((blocks >> BigInt(i)) & BigInt(1)) == BigInt(1);
+        }
+        this.wFirst = stateRepr.is_white_turn;
+    }
+
     move(pos) {
         if (this.wFirst) {this.blocks[parsePos(this.wPos)] = true; this.wPos = pos;}
         else {this.blocks[parsePos(this.bPos)] = true; this.bPos = pos;};
@@ -1,4 +1,7 @@
 var chess = new Chess();
+var moveHistory = [];
+var startRepr = null;
+var gameId = null;
 
 const delay = ms => new Promise(res => setTimeout(res, ms));
 
@@ -23,11 +26,13 @@ async function render() {
                 tableCell.onclick = async function() {
                     if (chess.end() != null) {return;}
                     chess.move([j, i]);
+                    moveHistory.push(j + i*8);
                     await render();
                     if (chess.end() != null) {return;}
                     await delay(50);
                     let botMove = await useBot(chess);
                     chess.move(botMove);
+                    moveHistory.push(botMove[0] + botMove[1]*8);
                     await render();
                 }
             };
@@ -38,6 +43,9 @@ async function render() {
         tableBody.appendChild(tableRow);
     }
     boardNode.appendChild(tableBody);
+    if (chess.end() != null) {
+        await showRecord();
+    }
     if (chess.end() === true)
     {
         let msg = document.createElement("span");
@@ -54,6 +62,41 @@ async function render() {
     }
 }
 
+async function showRecord() {
+    let record = await useRecord(startRepr, moveHistory);
+    let link = document.createElement("a");
+    link.setAttribute("href", "data:text/plain;charset=utf-8," + encodeURIComponent(record));
+    link.setAttribute("download", "game.qdr");
+    link.setAttribute("style", "color: white;");
+    link.innerText = "Download game record";
+    document.getElementById("container").appendChild(link);
+}
+
+async function newGame() {
+    let spec = new URLSearchParams(window.location.search).get("start");
+    let specInput = document.getElementById("start-spec");
+    try {
+        let data = await useStart(spec);
+        startRepr = data.state_repr;
+        gameId = data.game_id;
+        chess.setState(startRepr);
+        specInput.value = data.spec;
+    } catch (err) {
+        specInput.value = spec || "";
+        let msg = document.createElement("p");
+        msg.setAttribute("style", "color: white;");
+        msg.innerText = err.message;
+        document.getElementById("container").appendChild(msg);
+    }
+    await render();
+}
+
+function restartWithSpec() {
+    let params = new URLSearchParams(window.location.search);
+    params.set("start", document.getElementById("start-spec").value);
+    window.location.search = params.toString();
+}
+
 window.onload = function() {
-    render()
+    newGame()
 }
\ No newline at end of file
@@ -0,0 +1,23 @@
+//! Plays one game between two bots and prints it as a game record.
+//!
+//!     cargo run --example play_game -- basic3 random
+
+use qdrust::bot::base::bots_fight;
+use qdrust::qd::record::GameRecord;
+use qdrust::qd::utils::gsvd;
+use qdrust::{map_bot_string, GameState};
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let white_string = args.first().map(String::as_str).unwrap_or("basic3");
+    let black_string = args.get(1).map(String::as_str).unwrap_or("random");
+
+    let white = map_bot_string(white_string).expect("unknown white bot");
+    let black = map_bot_string(black_string).expect("unknown black bot");
+
+    let game = bots_fight(white.as_ref(), black.as_ref(), GameState::def_rand());
+
+    println!("{}", gsvd(&game.state()));
+    println!();
+    print!("{}", GameRecord::from_game(&game, white_string, black_string));
+}
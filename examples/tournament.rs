@@ -0,0 +1,27 @@
+//! Runs a small Elo tournament between a few bots.
+//!
+//!     cargo run --release --example tournament
+
+use qdrust::bot::elo::TournamentOptions;
+use qdrust::{map_bot_string, run_tournament, Bot};
+
+fn main() {
+    let names = ["random", "weak5", "basic1", "basic2"];
+    let bots: Vec<Box<dyn Bot>> = names
+        .iter()
+        .map(|name| map_bot_string(name).expect("unknown bot"))
+        .collect();
+
+    let options = TournamentOptions {
+        num_matchups: 400,
+        k_end: 8.,
+        seed: Some(1),
+        ..TournamentOptions::default()
+    };
+    let elos = run_tournament(bots, &options).expect("no start position");
+
+    let min_elo = elos.iter().cloned().fold(f64::INFINITY, f64::min);
+    for (name, elo) in names.iter().zip(elos) {
+        println!("{}: {:.0}", name, elo - min_elo);
+    }
+}
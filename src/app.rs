@@ -1,5 +1,12 @@
 pub mod battle;
 pub mod benchmark;
 pub mod enums;
+pub mod listbots;
 pub mod playbot;
-pub mod playbotcli;
\ No newline at end of file
+pub mod perft;
+pub mod playbotcli;
+pub mod replay;
+pub mod search;
+pub mod solve;
+pub mod tbgen;
+
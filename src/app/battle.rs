@@ -1,13 +1,20 @@
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::path::PathBuf;
 use std::thread;
 use std::time::Instant;
 use indicatif::{ProgressBar, ProgressStyle};
-use crate::bot::base::Bot;
-use crate::bot::collections::map_bot_string;
-use crate::bot::elo::run_tournament;
+use qdrust::bot::base::Bot;
+use qdrust::bot::registry::parse_bot;
+use qdrust::bot::tbbot::TablebaseBot;
+use qdrust::bot::elo::{run_tournament, run_tournament_recorded, PlayedGame, TournamentOptions};
+use qdrust::qd::record::{append_records, GameRecord};
+use qdrust::qd::startpos::StartPositionGenerator;
+use qdrust::rng::rng_from_seed;
+use crate::app::tbgen::load_tablebase;
 
 
+#[allow(clippy::too_many_arguments)]
 pub fn battle(
     bot_strings: Vec<String>, 
     num_matchups: usize, 
@@ -15,25 +22,39 @@ pub fn battle(
     k_start: f64,
     k_end: f64,
     sorted: bool,
+    save: Option<PathBuf>,
+    start_gen: StartPositionGenerator,
+    tablebase: Option<PathBuf>,
+    seed: Option<u64>,
 ) {
-    let bot_zip: Vec<(Option<Box<dyn Bot>>, String)> = 
-    bot_strings.clone().into_iter().map(
-            |x| (map_bot_string(&x), x.clone())
-        ).collect();
-    for (bot, string) in &bot_zip {
-        if bot.is_none() {
-            eprintln!("\"{}\" does not exist", string);
-            return ();
-        } 
+    let mut bots: Vec<Box<dyn Bot>> = Vec::new();
+    for string in &bot_strings {
+        match parse_bot(string) {
+            Ok(bot) => bots.push(bot),
+            Err(err) => {
+                eprintln!("Invalid bot \"{}\": {}", string, err);
+                return;
+            }
+        }
     }
-    let bots: Vec<Box<dyn Bot>> = 
-        bot_zip.into_iter().map(
-            |(x, _)| x.expect("")
-        ).collect();
+    let tablebase = match load_tablebase(tablebase) {
+        Ok(tablebase) => tablebase,
+        Err(err) => {
+            eprintln!("Failed to load tablebase: {}", err);
+            return;
+        }
+    };
+    let bots: Vec<Box<dyn Bot>> = bots.into_iter()
+        .map(|bot| TablebaseBot::wrap(bot, &tablebase))
+        .collect();
     if bots.len() <= 1 {
         eprintln!("You need at least 2 bots to battle");
         return;
     }
+    if let Err(err) = start_gen.try_generate_with(&mut rng_from_seed(seed)) {
+        eprintln!("Bad start position generator: {}", err);
+        return;
+    }
         
     let bar = Arc::new(ProgressBar::new(num_matchups as u64));
     bar.set_style(
@@ -65,19 +86,49 @@ pub fn battle(
             }
         });
     }
-    let elo_scores = run_tournament(
-        bots, 
-        num_matchups, 
+    let options = TournamentOptions {
+        num_matchups,
         k_start,
         k_end,
         num_threads,
-        &Some(prog_func));
+        start_gen,
+        progress: Some(prog_func),
+        seed,
+    };
+    let mut games: Vec<PlayedGame> = Vec::new();
+    let elo_scores = if save.is_some() {
+        run_tournament_recorded(bots, &options, &mut games)
+    } else {
+        run_tournament(bots, &options)
+    };
     bar.finish();
+    let elo_scores = match elo_scores {
+        Ok(elo_scores) => elo_scores,
+        Err(err) => {
+            eprintln!("Bad start position generator: {}", err);
+            return;
+        }
+    };
+
+    if let Some(path) = save {
+        let records: Vec<GameRecord> = games.iter().enumerate().map(|(round, played)| {
+            let mut record = GameRecord::from_game(
+                &played.game,
+                &bot_strings[played.white],
+                &bot_strings[played.black],
+            );
+            record.set_header("Round", &(round + 1).to_string());
+            record
+        }).collect();
+        if let Err(err) = append_records(&path, &records) {
+            eprintln!("Failed to save games to {}: {}", path.display(), err);
+        }
+    }
 
     let min_elo = elo_scores.iter().cloned().fold(f64::INFINITY, f64::min);
     let elo_scores: Vec<f64> = elo_scores.into_iter().map(|elo| elo - min_elo).collect();
 
-    let mut elo_zip: Vec<(String, f64)> = bot_strings.clone().into_iter().zip(elo_scores.into_iter()).collect();
+    let mut elo_zip: Vec<(String, f64)> = bot_strings.clone().into_iter().zip(elo_scores).collect();
     if sorted {
         elo_zip.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
     }
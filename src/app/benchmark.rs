@@ -1,44 +1,129 @@
 use std::io::{self, Read};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::thread;
 use std::time::Instant;
 use indicatif::{ProgressBar, ProgressStyle};
 
-use crate::bot::base::Bot;
-use crate::bot::collections::map_bot_string;
-use crate::bot::elo::run_benchmark;
+use qdrust::bot::base::{Bot, SearchInfo};
+use qdrust::bot::registry::{parse_bot, BotSpecError};
+use qdrust::bot::tbbot::TablebaseBot;
+use qdrust::bot::elo::{run_benchmark, TournamentOptions};
+use qdrust::qd::startpos::StartPositionGenerator;
+use qdrust::qd::state::GameState;
+use qdrust::rng::rng_from_seed;
+use crate::app::tbgen::load_tablebase;
 
 enum Exception {
-    InvalidBuffer
+    InvalidBuffer,
+    InvalidBot(String, BotSpecError),
 }
 
-fn buffer_to_oppo_bots_elos(buffer: &str) -> Result<(Vec<Box<dyn Bot>>, Vec<f64>), Exception> {
+/// Totals over the moves a `RecordingBot` made.
+struct MoveStats {
+    moves: u64,
+    nodes: u64,
+    depth_moves: u64,
+    depth: u64,
+    proven: u64,
+    micros: u64,
+}
+
+/// Passes moves through from `inner`, adding what it found to `stats`.
+#[derive(Clone)]
+struct RecordingBot {
+    inner: Box<dyn Bot>,
+    stats: Arc<[AtomicU64; 6]>,
+}
+
+impl RecordingBot {
+    fn new(inner: Box<dyn Bot>) -> Self {
+        Self { inner, stats: Arc::new(Default::default()) }
+    }
+
+    fn stats(&self) -> MoveStats {
+        let [moves, nodes, depth_moves, depth, proven, micros] =
+            self.stats.each_ref().map(|total| total.load(Ordering::Relaxed));
+        MoveStats { moves, nodes, depth_moves, depth, proven, micros }
+    }
+}
+
+impl Bot for RecordingBot {
+    fn decide(&self, state: GameState) -> u8 {
+        self.decide_with_info(state).best_move
+    }
+
+    fn decide_with_info(&self, state: GameState) -> SearchInfo {
+        let info = self.inner.decide_with_info(state);
+        let [moves, nodes, depth_moves, depth, proven, micros] = &*self.stats;
+        moves.fetch_add(1, Ordering::Relaxed);
+        nodes.fetch_add(info.nodes, Ordering::Relaxed);
+        if let Some(reached) = info.depth {
+            depth_moves.fetch_add(1, Ordering::Relaxed);
+            depth.fetch_add(reached as u64, Ordering::Relaxed);
+        }
+        proven.fetch_add(info.proven as u64, Ordering::Relaxed);
+        micros.fetch_add(info.time.as_micros() as u64, Ordering::Relaxed);
+        info
+    }
+
+    fn new_game(&mut self, start: GameState) {
+        self.inner.new_game(start);
+    }
+
+    fn observe_move(&mut self, state: GameState, to: u8) {
+        self.inner.observe_move(state, to);
+    }
+
+    fn game_over(&mut self, white_won: bool) {
+        self.inner.game_over(white_won);
+    }
+}
+
+fn print_stats(stats: &MoveStats) {
+    if stats.moves == 0 {
+        return;
+    }
+    let moves = stats.moves as f64;
+    eprint!(
+        "Per move: {:.2}ms, {:.0} nodes",
+        stats.micros as f64 / moves / 1000.,
+        stats.nodes as f64 / moves,
+    );
+    if stats.depth_moves > 0 {
+        eprint!(", depth {:.2}", stats.depth as f64 / stats.depth_moves as f64);
+    }
+    eprintln!(", {:.1}% proven", stats.proven as f64 / moves * 100.);
+}
+
+type OppoBotsElos = (Vec<Box<dyn Bot>>, Vec<f64>);
+
+fn buffer_to_oppo_bots_elos(buffer: &str) -> Result<OppoBotsElos, Exception> {
     let mut oppo_bots = Vec::new();
     let mut elos = Vec::new();
     for line in buffer.lines() {
-        let mut parts = line.split(':');
-        let name = parts.next().ok_or(Exception::InvalidBuffer)?;
-        let elo_str = parts.next().ok_or(Exception::InvalidBuffer)?;
+        // Bot strings may contain ':' themselves, so the Elo is after the last one.
+        let (name, elo_str) = line.rsplit_once(':').ok_or(Exception::InvalidBuffer)?;
         let elo: f64 = elo_str.trim().parse().map_err(|_| Exception::InvalidBuffer)?;
-
-        if let Some(bot) = map_bot_string(name) {
-            oppo_bots.push(bot);
-            elos.push(elo);
-        } else {
-            return Err(Exception::InvalidBuffer);
-        }
+        let bot = parse_bot(name).map_err(|err| Exception::InvalidBot(name.to_string(), err))?;
+        oppo_bots.push(bot);
+        elos.push(elo);
     }
 
     Ok((oppo_bots, elos))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn benchmark(
     bot_string: String,
     num_matchups: usize,
     num_threads: usize,
     k_start: f64,
     k_end: f64,
+    start_gen: StartPositionGenerator,
+    tablebase: Option<PathBuf>,
+    seed: Option<u64>,
 ) {
     let mut buffer = String::new();
     let res = io::stdin().read_to_string(&mut buffer);
@@ -48,21 +133,41 @@ pub fn benchmark(
     }
     let (oppo_bots, oppo_elos) = match buffer_to_oppo_bots_elos(&buffer) {
         Ok(data) => data,
-        Err(_) => {
+        Err(Exception::InvalidBuffer) => {
             eprintln!("Invalid input format");
             return;
         }
+        Err(Exception::InvalidBot(name, err)) => {
+            eprintln!("Invalid bot \"{}\": {}", name, err);
+            return;
+        }
     };
     if oppo_bots.is_empty() {
         eprintln!("No opponent bots provided");
         return;
     }
-    let bot = map_bot_string(&bot_string);
-    if bot.is_none() {
-        eprintln!("\"{}\" does not exist", bot_string);
+    let bot = match parse_bot(&bot_string) {
+        Ok(bot) => bot,
+        Err(err) => {
+            eprintln!("Invalid bot \"{}\": {}", bot_string, err);
+            return;
+        }
+    };
+    let bot = RecordingBot::new(bot);
+    let recorder = bot.clone();
+    let bot = Box::new(bot);
+    let tablebase = match load_tablebase(tablebase) {
+        Ok(tablebase) => tablebase,
+        Err(err) => {
+            eprintln!("Failed to load tablebase: {}", err);
+            return;
+        }
+    };
+    let bot = TablebaseBot::wrap(bot, &tablebase);
+    if let Err(err) = start_gen.try_generate_with(&mut rng_from_seed(seed)) {
+        eprintln!("Bad start position generator: {}", err);
         return;
     }
-    let bot = bot.unwrap();
 
     let bar = Arc::new(ProgressBar::new(num_matchups as u64));
     bar.set_style(
@@ -95,16 +200,24 @@ pub fn benchmark(
         });
     }
 
-    let elo = run_benchmark(
-        bot,
-        oppo_bots,
+    let options = TournamentOptions {
         num_matchups,
-        oppo_elos,
         k_start,
         k_end,
         num_threads,
-        &Some(prog_func)
-    );
+        start_gen,
+        progress: Some(prog_func),
+        seed,
+    };
+    let elo = run_benchmark(bot, oppo_bots, oppo_elos, &options);
     bar.finish();
+    let elo = match elo {
+        Ok(elo) => elo,
+        Err(err) => {
+            eprintln!("Bad start position generator: {}", err);
+            return;
+        }
+    };
+    print_stats(&recorder.stats());
     println!("{:.0}", elo);
 }
\ No newline at end of file
@@ -0,0 +1,18 @@
+use qdrust::bot::registry::BOTS;
+
+/// Prints every bot with its parameters and their defaults.
+pub fn list_bots() {
+    println!("Bot strings look like name or name(param=value,...), e.g. adapt(budget=4096,eval=region).");
+    for entry in BOTS {
+        println!();
+        println!("{}: {}", entry.name, entry.help);
+        let width = entry.params.iter().map(|spec| spec.name.len()).max().unwrap_or(0);
+        for spec in entry.params {
+            println!("  {:<width$}  {} (default {})", spec.name, spec.help, spec.default, width = width);
+            println!("  {:<width$}  {}", "", spec.kind, width = width);
+        }
+        if let Some(example) = entry.shorthand_example {
+            println!("  Short form: {}", example);
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use std::time::Instant;
+use qdrust::qd::notation::square_name;
+use qdrust::qd::perft::{perft, perft_divide};
+use qdrust::qd::state::GameState;
+
+pub fn run_perft(position: String, depth: u32, divide: bool) {
+    let state = if position == "startpos" {
+        GameState::def()
+    } else {
+        match GameState::from_notation(&position) {
+            Ok(state) => state,
+            Err(err) => {
+                eprintln!("Invalid position \"{}\": {}", position, err);
+                return;
+            }
+        }
+    };
+
+    let start = Instant::now();
+    let nodes = if divide {
+        let moves = perft_divide(&state, depth);
+        for (to, count) in &moves {
+            println!("{}: {}", square_name(*to), count);
+        }
+        println!();
+        println!("Moves: {}", moves.len());
+        moves.iter().map(|(_, count)| count).sum()
+    } else {
+        perft(&state, depth)
+    };
+    let elapsed = start.elapsed().as_secs_f64();
+
+    println!("Nodes: {}", nodes);
+    println!("Time: {:.3}s", elapsed);
+    if elapsed > 0. {
+        println!("Speed: {:.0} nodes/s", nodes as f64 / elapsed);
+    }
+}
@@ -3,26 +3,52 @@ use include_dir::{include_dir, Dir};
 use tera::{Tera, Context};
 use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer, Responder};
 use serde::{Deserialize, Serialize};
-use crate::qd::state::GameState;
-use crate::bot::base::Bot;
-use crate::bot::collections::map_bot_string;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use qdrust::qd::game::Game;
+use qdrust::qd::notation::square_name;
+use qdrust::qd::error::QdError;
+use qdrust::qd::record::{append_records, GameRecord};
+use qdrust::qd::startpos::StartPositionGenerator;
+use qdrust::qd::state::GameState;
+use qdrust::bot::base::{Bot, BotPlayer, SearchInfo};
+use qdrust::bot::registry::parse_bot;
+use qdrust::bot::tbbot::TablebaseBot;
+use qdrust::rng::with_rng;
+use crate::app::tbgen::load_tablebase;
 
 static STATIC_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/assets/static");
 static INDEX_FILE: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/index.html.tera"));
 
+/// The games being played, by the id `/start` handed out, with when each
+/// was last played in.
+type Players = HashMap<String, (Instant, BotPlayer<'static>)>;
+
+/// How many games the server keeps at once. Starting one more forgets the
+/// one played in least recently.
+const MAX_GAMES: usize = 256;
+
 struct AppData {
     bot: Box<dyn Bot>,
+    players: Arc<Mutex<Players>>,
+    bot_string: String,
     token: String,
     tera: Tera,
     use_token: bool,
+    save: Option<PathBuf>,
+    start_gen: StartPositionGenerator,
 }
 
+static SAVE_LOCK: Mutex<()> = Mutex::new(());
+
 #[derive(Deserialize)]
 struct Info {
     token: Option<String>
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 struct GameStateRepr {
     wqueen: u8,
     bqueen: u8,
@@ -31,11 +57,22 @@ struct GameStateRepr {
 }
 
 impl GameStateRepr {
-    fn to_game_state(&self) -> GameState {
-        GameState::new(
+    fn from_game_state(state: &GameState) -> Self {
+        Self {
+            wqueen: state.wqueen(),
+            bqueen: state.bqueen(),
+            blocks: state.blocks().to_string(),
+            is_white_turn: state.is_white_turn(),
+        }
+    }
+
+    fn to_game_state(&self) -> Result<GameState, QdError> {
+        let blocks = self.blocks.parse::<u64>()
+            .map_err(|_| QdError::BadBlocks(self.blocks.clone()))?;
+        GameState::try_new(
             Some(self.wqueen),
             Some(self.bqueen),
-            Some(u64::from_str_radix(&self.blocks, 10).unwrap()),
+            Some(blocks),
             Some(self.is_white_turn),
         )
     }
@@ -43,15 +80,82 @@ impl GameStateRepr {
 
 #[derive(Deserialize)]
 struct Data {
+    game_id: Option<String>,
     state_repr: GameStateRepr
 }
 
+/// What the bot found, with the line as square names and the time in
+/// milliseconds.
+#[derive(Serialize)]
+struct SearchInfoRepr {
+    score: Option<f64>,
+    pv: Vec<String>,
+    depth: Option<u32>,
+    nodes: u64,
+    time_ms: f64,
+    proven: bool,
+}
+
+impl SearchInfoRepr {
+    fn from_info(info: &SearchInfo) -> Self {
+        Self {
+            score: info.score,
+            pv: info.pv.iter().map(|&to| square_name(to)).collect(),
+            depth: info.depth,
+            nodes: info.nodes,
+            time_ms: info.time.as_secs_f64() * 1000.,
+            proven: info.proven,
+        }
+    }
+}
+
 #[derive(Serialize)]
 struct Response {
     move_made: u8,
+    info: SearchInfoRepr,
+    code: u32
+}
+
+#[derive(Deserialize)]
+struct RecordData {
+    game_id: Option<String>,
+    start: Option<GameStateRepr>,
+    moves: Vec<u8>
+}
+
+#[derive(Deserialize)]
+struct StartQuery {
+    spec: Option<String>
+}
+
+#[derive(Serialize)]
+struct StartResponse {
+    game_id: String,
+    state_repr: GameStateRepr,
+    spec: String,
+    code: u32
+}
+
+#[derive(Serialize)]
+struct RecordResponse {
+    record: String,
+    saved: bool,
     code: u32
 }
 
+fn bearer_token(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|auth_header| {
+            if auth_header.starts_with("Bearer ") {
+                Some(auth_header.trim_start_matches("Bearer ").to_string())
+            } else {
+                None
+            }
+        })
+}
+
 fn random_hex_string(len: usize) -> String {
     let mut rng = thread_rng();
     let bytes: Vec<u8> = (0..len).map(|_| rng.r#gen()).collect();
@@ -63,31 +167,144 @@ async fn bot_endpoint(
     req: HttpRequest,
     payload: web::Json<Data>
 ) -> impl Responder {
-    let token = req
-        .headers()
-        .get("Authorization")
-        .and_then(|h| h.to_str().ok())
-        .and_then(|auth_header| {
-            if auth_header.starts_with("Bearer ") {
-                Some(auth_header.trim_start_matches("Bearer ").to_string())
-            } else {
-                None
-            }
-        });
+    let token = bearer_token(&req);
     if token.is_none() && data.use_token {
         return HttpResponse::Unauthorized().body("Missing token");
     }
     if Some(data.token.clone()) != token && data.use_token {
         return HttpResponse::Unauthorized().body("Invalid token");
     }
-    let res = data.bot.decide(payload.state_repr.to_game_state());
+    let state = match payload.state_repr.to_game_state() {
+        Ok(state) => state,
+        Err(err) => return HttpResponse::BadRequest().body(err.to_string()),
+    };
+    // Search on a copy, so the lock is only held to look the game up and
+    // to store it back.
+    let known = payload.game_id.as_ref().and_then(|game_id| {
+        let players = data.players.lock().unwrap_or_else(|e| e.into_inner());
+        players.get(game_id).map(|(_, player)| player.clone())
+    });
+    let mut player = known.unwrap_or_else(|| BotPlayer::new(data.bot.as_ref(), state));
+    if !player.follow(&state) {
+        player = BotPlayer::new(data.bot.as_ref(), state);
+    }
+    let searched = web::block(move || {
+        let info = player.try_decide_with_info()?;
+        player.play(info.best_move);
+        Ok::<_, QdError>((info, player))
+    }).await;
+    let (info, player) = match searched {
+        Ok(Ok(searched)) => searched,
+        Ok(Err(err)) => return HttpResponse::BadRequest().body(err.to_string()),
+        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
+    };
+    if let Some(game_id) = &payload.game_id {
+        let mut players = data.players.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(entry) = players.get_mut(game_id) {
+            *entry = (Instant::now(), player);
+        }
+    }
     let response = Response {
-        move_made: res,
+        move_made: info.best_move,
+        info: SearchInfoRepr::from_info(&info),
         code: 200
     };
     HttpResponse::Ok().json(response)
 }
 
+async fn record_endpoint(
+    data: web::Data<AppData>,
+    req: HttpRequest,
+    payload: web::Json<RecordData>
+) -> impl Responder {
+    let token = bearer_token(&req);
+    if token.is_none() && data.use_token {
+        return HttpResponse::Unauthorized().body("Missing token");
+    }
+    if Some(data.token.clone()) != token && data.use_token {
+        return HttpResponse::Unauthorized().body("Invalid token");
+    }
+    let start = match &payload.start {
+        Some(repr) => match repr.to_game_state() {
+            Ok(state) => state,
+            Err(err) => return HttpResponse::BadRequest().body(err.to_string()),
+        },
+        None => GameState::def(),
+    };
+    let mut game = Game::new(start);
+    for &square in &payload.moves {
+        if let Err(err) = game.try_make_move(square) {
+            return HttpResponse::BadRequest().body(err.to_string());
+        }
+    }
+    if let Some(game_id) = &payload.game_id {
+        let mut players = data.players.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((_, player)) = players.get_mut(game_id) {
+            player.follow(&game.state());
+        }
+        if game.state().result().is_some() {
+            players.remove(game_id);
+        }
+    }
+    let record = GameRecord::from_game(&game, "human", &data.bot_string);
+    let mut saved = false;
+    if let Some(path) = &data.save {
+        let _guard = SAVE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        match append_records(path, std::slice::from_ref(&record)) {
+            Ok(()) => saved = true,
+            Err(err) => eprintln!("Failed to save game to {}: {}", path.display(), err),
+        }
+    }
+    HttpResponse::Ok().json(RecordResponse {
+        record: record.to_text(),
+        saved,
+        code: 200
+    })
+}
+
+async fn start_endpoint(
+    data: web::Data<AppData>,
+    req: HttpRequest,
+    query: web::Query<StartQuery>
+) -> impl Responder {
+    let token = bearer_token(&req);
+    if token.is_none() && data.use_token {
+        return HttpResponse::Unauthorized().body("Missing token");
+    }
+    if Some(data.token.clone()) != token && data.use_token {
+        return HttpResponse::Unauthorized().body("Invalid token");
+    }
+    let start_gen = match &query.spec {
+        Some(spec) => match spec.parse::<StartPositionGenerator>() {
+            Ok(start_gen) => start_gen,
+            Err(err) => return HttpResponse::BadRequest().body(err.to_string()),
+        },
+        None => data.start_gen,
+    };
+    let state = match web::block(move || with_rng(|rng| start_gen.try_generate_with(rng))).await {
+        Ok(Ok(state)) => state,
+        Ok(Err(err)) => return HttpResponse::BadRequest().body(err.to_string()),
+        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
+    };
+    let game_id = random_hex_string(16);
+    let player = BotPlayer::new(data.bot.as_ref(), state);
+    {
+        let mut players = data.players.lock().unwrap_or_else(|e| e.into_inner());
+        if players.len() >= MAX_GAMES
+            && let Some(stale) = players.iter().min_by_key(|(_, (used, _))| *used).map(|(id, _)| id.clone())
+        {
+            players.remove(&stale);
+        }
+        players.insert(game_id.clone(), (Instant::now(), player));
+    }
+    HttpResponse::Ok().json(StartResponse {
+        game_id,
+        state_repr: GameStateRepr::from_game_state(&state),
+        spec: start_gen.to_string(),
+        code: 200
+    })
+}
+
 async fn index_endpoint(
     data: web::Data<AppData>,
     info: web::Query<Info>
@@ -126,33 +343,57 @@ pub async fn play_bot(
     bot_string: String, 
     port: u16, 
     use_token: bool,
-    open_browser: bool
+    open_browser: bool,
+    save: Option<PathBuf>,
+    start_gen: StartPositionGenerator,
+    tablebase: Option<PathBuf>
 ) -> std::io::Result<()> {
     let token = random_hex_string(16);
-    let bot = map_bot_string(&bot_string);
-    if bot.is_none() {
-        eprintln!("\"{}\" does not exist", bot_string);
+    let bot = match parse_bot(&bot_string) {
+        Ok(bot) => bot,
+        Err(err) => {
+            eprintln!("Invalid bot \"{}\": {}", bot_string, err);
+            return Ok(());
+        }
+    };
+    let tablebase = match load_tablebase(tablebase) {
+        Ok(tablebase) => tablebase,
+        Err(err) => {
+            eprintln!("Failed to load tablebase: {}", err);
+            return Ok(());
+        }
+    };
+    let bot = TablebaseBot::wrap(bot, &tablebase);
+    if let Err(err) = with_rng(|rng| start_gen.try_generate_with(rng)) {
+        eprintln!("Bad start position generator: {}", err);
         return Ok(());
     }
-    let bot = bot.unwrap();
     
     let mut tera = Tera::default();
     tera.add_raw_template("index", INDEX_FILE)
         .expect("Failed to add template");
+    let players = Arc::new(Mutex::new(Players::new()));
     let app = {
         let token = token.clone();
+        let bot_string = bot_string.clone();
         move || {
             App::new()
                 .app_data(web::Data::new(AppData {
                     bot: bot.clone(),
+                    players: players.clone(),
+                    bot_string: bot_string.clone(),
                     token: token.clone(),
                     tera: tera.clone(),
-                    use_token: use_token.clone()
+                    use_token,
+                    save: save.clone(),
+                    start_gen,
                 }))
                 .route("/", web::get().to(index_endpoint))
                 .route("/index.html", web::get().to(index_endpoint))
                 .route("/static/{filename:.*}", web::get().to(serve_static))
                 .route("/bot", web::post().to(bot_endpoint))
+                .route("/record", web::post().to(record_endpoint))
+                .route("/start", web::get().to(start_endpoint))
         }
     };
     let access_url = if use_token {
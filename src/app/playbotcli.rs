@@ -1,58 +1,149 @@
 use std::io::{self, Write};
+use std::path::PathBuf;
 use rand::Rng;
 use regex::Regex;
 use crate::app::enums::ColorMode;
-use crate::bot::collections::map_bot_string;
-use crate::qd::legalcomp::get_possible_legal_moves;
-use crate::qd::state::GameState;
-use crate::qd::utils::gsvd;
+use crate::app::tbgen::load_tablebase;
+use qdrust::bot::base::BotPlayer;
+use qdrust::bot::registry::parse_bot;
+use qdrust::bot::tbbot::TablebaseBot;
+use qdrust::qd::error::QdError;
+use qdrust::qd::game::Game;
+use qdrust::qd::record::{append_records, GameRecord};
+use qdrust::qd::state::GameState;
+use qdrust::qd::utils::gsvd;
+use qdrust::rng::{seed_thread, with_rng};
 
-pub fn play_bot_cli(bot_string: String, color: ColorMode) {
-    let bot = map_bot_string(&bot_string);
-    if bot.is_none() {
-        eprintln!("\"{}\" does not exist", bot_string);
-        return;
+/// Takes back moves until it is the player's turn again, undoing at least
+/// one of the player's own moves.
+fn take_back(game: &mut Game, color: bool) -> bool {
+    let can_undo_own = game.moves().len() > usize::from(game.start().is_white_turn() != color);
+    if !can_undo_own {
+        return false;
     }
+    game.undo();
+    while game.state().is_white_turn() != color && game.can_undo() {
+        game.undo();
+    }
+    true
+}
+
+/// Redoes the player's next move and the bot's reply to it, if any.
+fn replay_forward(game: &mut Game, color: bool) -> bool {
+    if !game.can_redo() {
+        return false;
+    }
+    game.redo();
+    while game.state().is_white_turn() != color && game.can_redo() {
+        game.redo();
+    }
+    true
+}
+
+pub fn play_bot_cli(
+    bot_string: String,
+    color: ColorMode,
+    position: Option<String>,
+    save: Option<PathBuf>,
+    tablebase: Option<PathBuf>,
+    seed: Option<u64>,
+) {
+    if let Some(seed) = seed {
+        seed_thread(seed);
+    }
+    let bot = match parse_bot(&bot_string) {
+        Ok(bot) => bot,
+        Err(err) => {
+            eprintln!("Invalid bot \"{}\": {}", bot_string, err);
+            return;
+        }
+    };
+    let start = match position {
+        Some(notation) => match GameState::from_notation(&notation)
+            .map_err(QdError::from)
+            .and_then(|state| state.validate().map(|_| state)) {
+            Ok(state) => state,
+            Err(err) => {
+                eprintln!("Invalid position \"{}\": {}", notation, err);
+                return;
+            }
+        },
+        None => GameState::def(),
+    };
     let color = match color {
         ColorMode::White => true,
         ColorMode::Black => false,
-        ColorMode::Random => {
-            let mut rng = rand::thread_rng();
-            rng.gen_bool(0.5)
+        ColorMode::Random => with_rng(|rng| rng.gen_bool(0.5)),
+    };
+    let tablebase = match load_tablebase(tablebase) {
+        Ok(tablebase) => tablebase,
+        Err(err) => {
+            eprintln!("Failed to load tablebase: {}", err);
+            return;
         }
     };
-    let bot = bot.unwrap();
+    let bot = TablebaseBot::wrap(bot, &tablebase);
     println!("You are playing against {} as {}", bot_string, if color { "white" } else { "black" });
-    let mut game = GameState::def();
+    println!("Enter a square such as e4, or \"undo\" / \"redo\" to take moves back.");
+    let re = Regex::new(r"^[a-h][1-8]$").unwrap();
+    let mut game = Game::new(start);
+    let mut player = BotPlayer::new(bot.as_ref(), start);
     while game.result().is_none() {
-        if game.is_white_turn != color {
-            game.make_move(bot.decide(game.clone()));
+        if game.state().is_white_turn() != color {
+            // Taking moves back starts the bot on a new game from here.
+            if !player.follow(&game.state()) {
+                player = BotPlayer::new(bot.as_ref(), game.state());
+            }
+            let info = player.decide_with_info();
+            println!("Bot played {}", info);
+            game.make_move(info.best_move);
+            player.play(info.best_move);
             continue;
         }
-        println!("{}", gsvd(&game));
+        println!("{}", gsvd(&game.state()));
         let mut input = String::new();
-        let re = Regex::new(r"^[a-h][1-8]$").unwrap();
         print!("Your move: ");
         io::stdout().flush().unwrap();
         io::stdin().read_line(&mut input).expect("Failed to read line");
         input = input.trim().to_string();
+        if input == "undo" {
+            if !take_back(&mut game, color) {
+                println!("Nothing to undo.");
+            }
+            continue;
+        }
+        if input == "redo" {
+            if !replay_forward(&mut game, color) {
+                println!("Nothing to redo.");
+            }
+            continue;
+        }
         if !re.is_match(&input) {
             println!("Invalid move format.");
             continue;
         }
-        let file = input.chars().nth(0).unwrap() as u8 - b'a';
+        let file = input.chars().next().unwrap() as u8 - b'a';
         let rank = input.chars().nth(1).unwrap() as u8 - b'1';
         let move_to = rank * 8 + file;
-        if get_possible_legal_moves(&game) & 1 << move_to == 0 {
-            println!("Illegal move.");
+        if let Err(err) = game.try_make_move(move_to) {
+            println!("Illegal move: {}.", err);
             continue;
         }
-        game.make_move(move_to);
     }
-    println!("{}", gsvd(&game));
+    player.follow(&game.state());
+    println!("{}", gsvd(&game.state()));
+    println!("Final position: {}", game.state());
     if game.result() == Some(color) {
         println!("You won!");
     } else {
         println!("You lost.");
     }
+    if let Some(path) = save {
+        let (white, black) = if color { ("human", bot_string.as_str()) } else { (bot_string.as_str(), "human") };
+        let record = GameRecord::from_game(&game, white, black);
+        match append_records(&path, &[record]) {
+            Ok(()) => println!("Game saved to {}", path.display()),
+            Err(err) => eprintln!("Failed to save game to {}: {}", path.display(), err),
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,63 @@
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use qdrust::qd::notation::square_name;
+use qdrust::qd::record::load_records;
+use qdrust::qd::utils::gsvd;
+
+pub fn replay(path: PathBuf, game_index: usize, no_pause: bool) {
+    let records = match load_records(&path) {
+        Ok(records) => records,
+        Err(err) => {
+            eprintln!("Failed to read {}: {}", path.display(), err);
+            return;
+        }
+    };
+    if game_index == 0 || game_index > records.len() {
+        eprintln!("Game {} does not exist ({} games in file)", game_index, records.len());
+        return;
+    }
+    let record = &records[game_index - 1];
+    let mut game = match record.to_game() {
+        Ok(game) => game,
+        Err(err) => {
+            eprintln!("Invalid game: {}", err);
+            return;
+        }
+    };
+    while game.undo().is_some() {}
+
+    println!("{} (white) vs {} (black), {}", record.white, record.black, record.date);
+    for (key, value) in &record.extra_headers {
+        println!("{}: {}", key, value);
+    }
+    println!();
+    println!("{}", gsvd(&game.state()));
+
+    let stdin = io::stdin();
+    let mut ply = 0;
+    while game.can_redo() {
+        if !no_pause {
+            print!("[Enter] next move, [q] quit: ");
+            io::stdout().flush().unwrap();
+            let mut input = String::new();
+            if stdin.lock().read_line(&mut input).unwrap_or(0) == 0 || input.trim() == "q" {
+                return;
+            }
+        }
+        let mv = game.redo().unwrap();
+        ply += 1;
+        println!();
+        println!("{}. {} {} -> {}",
+            ply,
+            if game.state().is_white_turn() { "black" } else { "white" },
+            square_name(mv.from),
+            square_name(mv.to));
+        println!("{}", gsvd(&game.state()));
+    }
+    println!();
+    match record.result {
+        Some(true) => println!("White wins ({})", record.termination.as_str()),
+        Some(false) => println!("Black wins ({})", record.termination.as_str()),
+        None => println!("Unfinished"),
+    }
+}
@@ -0,0 +1,124 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use qdrust::bot::eval::{parse_evaluator, Evaluator};
+use qdrust::bot::ordering::MoveOrdering;
+use qdrust::bot::search::{DepthLimit, SearchResult, Searcher, MAX_DEPTH};
+use qdrust::bot::tt::TranspositionTable;
+use qdrust::qd::notation::square_name;
+use qdrust::qd::state::GameState;
+use qdrust::rng::seed_thread;
+
+/// Move orderings to compare, each adding one heuristic to the last. The
+/// second is how the search ordered moves before killers and history.
+const ORDERINGS: [(&str, MoveOrdering); 6] = [
+    ("by square", MoveOrdering::NONE),
+    ("tt move", MoveOrdering { tt_move: true, ..MoveOrdering::NONE }),
+    ("+ captures", MoveOrdering { captures: true, tt_move: true, ..MoveOrdering::NONE }),
+    ("+ killers", MoveOrdering { captures: true, tt_move: true, killers: true, ..MoveOrdering::NONE }),
+    ("+ history", MoveOrdering { static_score: false, ..MoveOrdering::ALL }),
+    ("+ static", MoveOrdering::ALL),
+];
+
+/// Deepens to `depth` under each move ordering, with the same random noise
+/// and a fresh table each time, and prints the node counts.
+fn compare_orderings(state: &GameState, depth: u32, evaluator: Arc<dyn Evaluator>, tt_mb: usize) {
+    let far = Instant::now() + Duration::from_secs(1 << 30);
+    let mut baseline = None;
+    println!("{:<12} {:>12} {:>9} {:>10}", "Ordering", "Nodes", "Time", "vs tt move");
+    for (name, ordering) in ORDERINGS {
+        seed_thread(0);
+        let tt = (tt_mb > 0).then(|| Arc::new(TranspositionTable::new(tt_mb)));
+        let searcher = Searcher::new(evaluator.clone(), DepthLimit(depth)).ordering(ordering).tt(tt);
+        let start = Instant::now();
+        let (result, _) = searcher.deepen(state, far);
+        let elapsed = start.elapsed().as_secs_f64();
+        if ordering == ORDERINGS[1].1 {
+            baseline = Some(result.nodes);
+        }
+        let change = match baseline {
+            Some(base) => format!("{:+.1}%", (result.nodes as f64 / base as f64 - 1.) * 100.),
+            None => String::new(),
+        };
+        println!("{:<12} {:>12} {:>8.3}s {:>10}", name, result.nodes, elapsed, change);
+    }
+}
+
+fn print_result(result: &SearchResult, depth: u32, elapsed: f64) {
+    match result.best_move {
+        Some(to) => println!("Best move: {}", square_name(to)),
+        None => println!("Best move: none"),
+    }
+    println!("Score: {:.3}{}", result.score, if result.is_proven() { " (proven)" } else { "" });
+    println!("Depth: {}", depth);
+    println!("Nodes: {}", result.nodes);
+    println!("Time: {:.3}s", elapsed);
+    if elapsed > 0. {
+        println!("Speed: {:.0} nodes/s", result.nodes as f64 / elapsed);
+    }
+    match result.tt_hit_rate() {
+        Some(rate) => println!("TT hits: {} / {} ({:.1}%)", result.tt_hits, result.tt_probes, rate * 100.),
+        None => println!("TT hits: no table"),
+    }
+}
+
+/// Runs one search from `position` and prints its statistics. With `time`
+/// the search deepens until the time runs out, up to `depth` if given.
+/// With more than one thread it always deepens, sharing the table.
+pub fn run_search(
+    position: String,
+    depth: Option<u32>,
+    time: Option<u64>,
+    eval: String,
+    tt_mb: usize,
+    compare_ordering: bool,
+    num_threads: usize,
+) {
+    let state = if position == "startpos" {
+        GameState::def()
+    } else {
+        match GameState::from_notation(&position) {
+            Ok(state) => state,
+            Err(err) => {
+                eprintln!("Invalid position \"{}\": {}", position, err);
+                return;
+            }
+        }
+    };
+    if state.result().is_some() {
+        eprintln!("The game is already over");
+        return;
+    }
+    let evaluator = match parse_evaluator(&eval) {
+        Ok(evaluator) => evaluator,
+        Err(err) => {
+            eprintln!("Invalid evaluator \"{}\": {}", eval, err);
+            return;
+        }
+    };
+    let depth = depth.unwrap_or(if time.is_some() { MAX_DEPTH } else { 4 });
+    if depth == 0 {
+        eprintln!("Depth must be at least 1");
+        return;
+    }
+
+    if compare_ordering {
+        if time.is_some() {
+            eprintln!("--compare-ordering searches to a fixed depth and takes no --time");
+            return;
+        }
+        compare_orderings(&state, depth, evaluator, tt_mb);
+        return;
+    }
+
+    let tt = (tt_mb > 0).then(|| Arc::new(TranspositionTable::new(tt_mb)));
+    let searcher = Searcher::new(evaluator, DepthLimit(depth)).tt(tt);
+    let start = Instant::now();
+    let (result, reached) = match time {
+        Some(ms) => searcher.deepen_parallel(&state, start + Duration::from_millis(ms), num_threads),
+        None if num_threads > 1 => {
+            searcher.deepen_parallel(&state, start + Duration::from_secs(1 << 30), num_threads)
+        }
+        None => (searcher.search(&state), depth),
+    };
+    print_result(&result, reached, start.elapsed().as_secs_f64());
+}
@@ -0,0 +1,155 @@
+use std::path::PathBuf;
+use std::time::Instant;
+use qdrust::bot::base::Bot;
+use qdrust::bot::registry::parse_bot;
+use qdrust::bot::pns::Solver;
+use qdrust::qd::notation::square_name;
+use qdrust::qd::startpos::StartPositionGenerator;
+use qdrust::qd::state::GameState;
+use qdrust::rng::{rng_from_seed, seed_thread};
+use crate::app::tbgen::load_tablebase;
+
+fn color_name(white: bool) -> &'static str {
+    if white { "White" } else { "Black" }
+}
+
+/// What the bot's move in a proven position does to the result.
+enum Verdict {
+    Keeps,
+    Throws,
+    Unknown,
+}
+
+/// Checks `bot`'s move in `state`, whose winner is `winner`.
+fn check_move(solver: &mut Solver, bot: &dyn Bot, state: &GameState, winner: bool) -> (u8, Verdict) {
+    let to = bot.decide(*state);
+    let mut child = *state;
+    child.make_move(to);
+    let verdict = match solver.prove(&child) {
+        Some(child_mover_wins) => {
+            if (child_mover_wins == child.is_white_turn()) == winner { Verdict::Keeps } else { Verdict::Throws }
+        }
+        None => Verdict::Unknown,
+    };
+    (to, verdict)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn solve(
+    position: String,
+    count: usize,
+    memory: usize,
+    nodes: Option<u64>,
+    start_gen: StartPositionGenerator,
+    tablebase: Option<PathBuf>,
+    check_bot: Option<String>,
+    seed: Option<u64>,
+) {
+    if let Some(seed) = seed {
+        seed_thread(seed);
+    }
+    let states = match position.as_str() {
+        "startpos" => vec![GameState::def()],
+        "random" => {
+            let mut rng = rng_from_seed(seed);
+            let mut states = Vec::new();
+            for _ in 0..count {
+                match start_gen.try_generate_with(&mut rng) {
+                    Ok(state) => states.push(state),
+                    Err(err) => {
+                        eprintln!("Invalid start position generator \"{}\": {}", start_gen, err);
+                        return;
+                    }
+                }
+            }
+            states
+        }
+        notation => match GameState::from_notation(notation) {
+            Ok(state) => vec![state],
+            Err(err) => {
+                eprintln!("Invalid position \"{}\": {}", notation, err);
+                return;
+            }
+        },
+    };
+    let bot = match &check_bot {
+        Some(bot_string) => match parse_bot(bot_string) {
+            Ok(bot) => Some(bot),
+            Err(err) => {
+                eprintln!("Invalid bot \"{}\": {}", bot_string, err);
+                return;
+            }
+        },
+        None => None,
+    };
+    let tablebase = match load_tablebase(tablebase) {
+        Ok(tablebase) => tablebase,
+        Err(err) => {
+            eprintln!("Failed to load tablebase: {}", err);
+            return;
+        }
+    };
+
+    let mut wins = [0usize; 2];
+    let mut unknown = 0;
+    let mut kept = 0;
+    let mut checked = 0;
+    for (i, state) in states.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("Position: {}", state.to_notation());
+        let start = Instant::now();
+        let mut solver = Solver::new(memory, nodes.unwrap_or(u64::MAX))
+            .with_tablebase(tablebase.clone());
+        let result = solver.solve(state);
+        let elapsed = start.elapsed().as_secs_f64();
+
+        match result.winner {
+            Some(winner) => {
+                wins[winner as usize] += 1;
+                println!("Result: {} wins", color_name(winner));
+                let line: Vec<String> = result.line.iter().map(|&to| square_name(to)).collect();
+                println!("Line: {}{}", line.join(" "), if result.complete { "" } else { " ..." });
+            }
+            None => {
+                unknown += 1;
+                println!("Result: unknown (node or memory limit reached)");
+            }
+        }
+        println!("Nodes: {}", result.nodes);
+        println!("Time: {:.3}s", elapsed);
+
+        if let (Some(bot), Some(winner)) = (&bot, result.winner) {
+            if state.result().is_some() {
+                continue;
+            }
+            let (to, verdict) = check_move(&mut solver, bot.as_ref(), state, winner);
+            let message = match verdict {
+                Verdict::Keeps if state.is_white_turn() == winner => "keeps the win",
+                Verdict::Keeps => "loses anyway",
+                Verdict::Throws => "throws away the win",
+                Verdict::Unknown => "could not be checked",
+            };
+            match verdict {
+                Verdict::Keeps => {
+                    checked += 1;
+                    kept += 1;
+                }
+                Verdict::Throws => checked += 1,
+                Verdict::Unknown => {}
+            }
+            println!("{} plays {}: {}", check_bot.as_deref().unwrap(), square_name(to), message);
+        }
+    }
+
+    if states.len() > 1 {
+        println!();
+        println!("White wins: {}", wins[1]);
+        println!("Black wins: {}", wins[0]);
+        println!("Unknown: {}", unknown);
+        if bot.is_some() {
+            println!("Bot kept the result in {}/{} positions", kept, checked);
+        }
+    }
+}
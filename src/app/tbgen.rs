@@ -0,0 +1,34 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+use qdrust::tablebase::index::level_size;
+use qdrust::tablebase::probe::{Tablebase, TbError};
+
+pub fn tb_gen(path: PathBuf, max_empty: u8, num_threads: usize) {
+    let start = Instant::now();
+    let prog_func: Box<dyn Fn(u8)> = Box::new(|level| {
+        println!(
+            "Level {}: {} positions, {:.3}s",
+            level,
+            level_size(level),
+            start.elapsed().as_secs_f64()
+        );
+    });
+    let tablebase = match Tablebase::generate(max_empty, num_threads, &Some(prog_func)) {
+        Ok(tablebase) => tablebase,
+        Err(err) => {
+            eprintln!("Failed to generate tablebase: {}", err);
+            return;
+        }
+    };
+    if let Err(err) = tablebase.save(&path) {
+        eprintln!("Failed to save tablebase to {}: {}", path.display(), err);
+        return;
+    }
+    println!("Saved {} positions to {}", tablebase.len(), path.display());
+}
+
+/// Loads the tablebase given with `--tablebase`, if any.
+pub fn load_tablebase(path: Option<PathBuf>) -> Result<Option<Arc<Tablebase>>, TbError> {
+    path.map(|path| Tablebase::load(&path).map(Arc::new)).transpose()
+}
@@ -1,3 +1,11 @@
 pub mod base;
 pub mod elo;
-pub mod collections;
\ No newline at end of file
+pub mod collections;
+pub mod separated;
+pub mod tbbot;
+pub mod pns;
+pub mod search;
+pub mod eval;
+pub mod tt;
+pub mod ordering;
+pub mod registry;
\ No newline at end of file
@@ -1,26 +1,264 @@
-use rand::{thread_rng, Rng};
+use std::fmt;
+use std::time::{Duration, Instant};
+use rand::Rng;
 use dyn_clone::DynClone;
+use crate::qd::error::QdError;
+use crate::qd::game::Game;
+use crate::qd::legalcomp::get_possible_legal_moves;
+use crate::qd::notation::square_name;
 use crate::qd::state::GameState;
+use crate::rng::with_rng;
+
+/// What a bot found out while picking a move.
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct SearchInfo {
+    pub best_move: u8,
+    /// From white's point of view, if the bot scores positions.
+    pub score: Option<f64>,
+    /// The line the bot expects, starting with `best_move`.
+    pub pv: Vec<u8>,
+    /// Plies searched in full, for bots that search by depth.
+    pub depth: Option<u32>,
+    pub nodes: u64,
+    pub time: Duration,
+    /// Whether the score is a forced win for either side.
+    pub proven: bool,
+}
+
+impl SearchInfo {
+    /// Info for a bot that tells nothing but its move.
+    pub fn from_move(best_move: u8, time: Duration) -> Self {
+        Self { best_move, score: None, pv: vec![best_move], depth: None, nodes: 0, time, proven: false }
+    }
+}
+
+impl fmt::Display for SearchInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", square_name(self.best_move))?;
+        if let Some(score) = self.score {
+            write!(f, ", score {:+.2}{}", score, if self.proven { " (proven)" } else { "" })?;
+        }
+        if let Some(depth) = self.depth {
+            write!(f, ", depth {}", depth)?;
+        }
+        if self.nodes > 0 {
+            write!(f, ", {} nodes", self.nodes)?;
+        }
+        write!(f, ", {:.3}s", self.time.as_secs_f64())?;
+        if self.pv.len() > 1 {
+            let pv: Vec<String> = self.pv.iter().map(|&to| square_name(to)).collect();
+            write!(f, ", pv {}", pv.join(" "))?;
+        }
+        Ok(())
+    }
+}
 
 pub trait Bot: Send + Sync + DynClone {
+    /// Picks a move. `state` must be valid and the game not over.
     fn decide(&self, state: GameState) -> u8;
+
+    /// Like `decide`, but also reports what the bot found. Bots that do
+    /// not override it report only their move and the time taken.
+    fn decide_with_info(&self, state: GameState) -> SearchInfo {
+        let start = Instant::now();
+        let best_move = self.decide(state);
+        SearchInfo::from_move(best_move, start.elapsed())
+    }
+
+    /// Like `decide`, but reports an invalid or finished position as an
+    /// error instead of panicking.
+    fn try_decide(&self, state: GameState) -> Result<u8, QdError> {
+        check_playable(&state)?;
+        Ok(self.decide(state))
+    }
+
+    /// `decide_with_info` with the checks of `try_decide`.
+    fn try_decide_with_info(&self, state: GameState) -> Result<SearchInfo, QdError> {
+        check_playable(&state)?;
+        Ok(self.decide_with_info(state))
+    }
+
+    /// Called on a fresh copy of the bot before it plays a game from
+    /// `start`. State kept for the game, such as a transposition table,
+    /// belongs here rather than in the shared bot. See [`BotPlayer`].
+    fn new_game(&mut self, _start: GameState) {}
+
+    /// Called after every move of the game, the bot's own included, with
+    /// the position before it.
+    fn observe_move(&mut self, _state: GameState, _to: u8) {}
+
+    /// Called once the game is over.
+    fn game_over(&mut self, _white_won: bool) {}
 }
 
 dyn_clone::clone_trait_object!(Bot);
 
-pub fn bots_fight_rand(a: &dyn Bot, b: &dyn Bot) -> bool {
-    let mut rng = thread_rng();
-    let flip = rng.gen_bool(0.5);
-    let (white, black) = if flip { (b, a) } else { (a, b) };
-    let mut state = GameState::def_rand();
-    while state.result() == None {
-        if state.is_white_turn {
-            let move_to = white.decide(state);
-            state.make_move(move_to);
+fn check_playable(state: &GameState) -> Result<(), QdError> {
+    state.validate()?;
+    if state.result().is_some() {
+        return Err(QdError::GameOver);
+    }
+    Ok(())
+}
+
+/// A copy of a bot playing one game, told about every move in it.
+#[derive(Clone)]
+pub struct BotPlayer<'a> {
+    bot: Box<dyn Bot + 'a>,
+    state: GameState,
+}
+
+impl<'a> BotPlayer<'a> {
+    /// Copies `prototype` and starts it on a game from `start`.
+    pub fn new(prototype: &(dyn Bot + 'a), start: GameState) -> Self {
+        let mut bot = dyn_clone::clone_box(prototype);
+        bot.new_game(start);
+        Self { bot, state: start }
+    }
+
+    /// The position the bot knows of.
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+
+    /// The bot's move in the current position.
+    pub fn decide(&self) -> u8 {
+        self.bot.decide(self.state)
+    }
+
+    pub fn decide_with_info(&self) -> SearchInfo {
+        self.bot.decide_with_info(self.state)
+    }
+
+    pub fn try_decide_with_info(&self) -> Result<SearchInfo, QdError> {
+        self.bot.try_decide_with_info(self.state)
+    }
+
+    /// Tells the bot that `to` was played, and that the game is over if
+    /// it ended with that.
+    pub fn play(&mut self, to: u8) {
+        self.bot.observe_move(self.state, to);
+        self.state.make_move(to);
+        if let Some(white_won) = self.state.result() {
+            self.bot.game_over(white_won);
+        }
+    }
+
+    /// Brings the bot up to `state` if that is the current position or
+    /// one move after it. Returns false if it is neither.
+    pub fn follow(&mut self, state: &GameState) -> bool {
+        if self.state == *state {
+            return true;
+        }
+        if self.state.result().is_some() {
+            return false;
+        }
+        let mut moves = get_possible_legal_moves(&self.state);
+        while moves != 0 {
+            let to = moves.trailing_zeros() as u8;
+            moves &= moves - 1;
+            let mut next = self.state;
+            next.make_move(to);
+            if next == *state {
+                self.play(to);
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// Plays a game between fresh copies of `white` and `black`.
+pub fn bots_fight(white: &dyn Bot, black: &dyn Bot, start: GameState) -> Game {
+    let mut white = BotPlayer::new(white, start);
+    let mut black = BotPlayer::new(black, start);
+    let mut game = Game::new(start);
+    while game.result().is_none() {
+        let move_to = if game.state().is_white_turn() {
+            white.decide()
         } else {
-            let move_to = black.decide(state);
-            state.make_move(move_to);
+            black.decide()
+        };
+        game.make_move(move_to);
+        white.play(move_to);
+        black.play(move_to);
+    }
+    game
+}
+
+/// Plays `a` against `b` from `start` with random colors. Returns whether
+/// `a` won, whether `a` played white, and the game itself.
+pub fn bots_fight_rand_game(a: &dyn Bot, b: &dyn Bot, start: GameState) -> (bool, bool, Game) {
+    let flip = with_rng(|rng| rng.gen_bool(0.5));
+    let (white, black) = if flip { (b, a) } else { (a, b) };
+    let game = bots_fight(white, black, start);
+    (game.result().unwrap() != flip, !flip, game)
+}
+
+pub fn bots_fight_rand(a: &dyn Bot, b: &dyn Bot, start: GameState) -> bool {
+    bots_fight_rand_game(a, b, start).0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use super::*;
+    use crate::bot::collections::random::RandomBot;
+    use crate::rng::seed_thread;
+
+    /// Plays randomly and logs the hooks it sees.
+    #[derive(Clone, Default)]
+    struct LoggingBot {
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Bot for LoggingBot {
+        fn decide(&self, state: GameState) -> u8 {
+            RandomBot::new().decide(state)
+        }
+
+        fn new_game(&mut self, start: GameState) {
+            self.log.lock().unwrap().push(format!("new {}", start));
         }
+
+        fn observe_move(&mut self, _state: GameState, to: u8) {
+            self.log.lock().unwrap().push(format!("move {}", to));
+        }
+
+        fn game_over(&mut self, white_won: bool) {
+            self.log.lock().unwrap().push(format!("over {}", white_won));
+        }
+    }
+
+    #[test]
+    fn test_hooks() {
+        seed_thread(91);
+        let white = LoggingBot::default();
+        let black = LoggingBot::default();
+        let start = GameState::def();
+        let game = bots_fight(&white, &black, start);
+        let mut expected = vec![format!("new {}", start)];
+        expected.extend(game.moves().iter().map(|mv| format!("move {}", mv.to)));
+        expected.push(format!("over {}", game.result().unwrap()));
+        assert_eq!(*white.log.lock().unwrap(), expected);
+        assert_eq!(*black.log.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_follow() {
+        let bot = LoggingBot::default();
+        let start = GameState::def();
+        let mut player = BotPlayer::new(&bot, start);
+        let mut state = start;
+        state.make_move(12);
+        assert!(player.follow(&start));
+        assert!(player.follow(&state));
+        assert_eq!(player.state(), state);
+        state.make_move(51);
+        state.make_move(20);
+        assert!(!player.follow(&state));
+        assert_eq!(bot.log.lock().unwrap().len(), 2);
     }
-    state.result().unwrap() != flip
-}
\ No newline at end of file
+}
@@ -1,32 +1,39 @@
 use crate::bot::base::Bot;
+use crate::bot::registry::parse_bot;
 
 pub mod random;
 pub mod weak;
 pub mod basic;
 pub mod adapt;
+pub mod timed;
+pub mod mcts;
 
+/// Maps a bot string to a bot, or `None` if it is not valid. See
+/// [`parse_bot`], which also says what is wrong with it.
 pub fn map_bot_string(name: &str) -> Option<Box<dyn Bot>> {
-    if name == "random" { Some(Box::new(random::RandomBot::new())) } 
-    else if let Some(num) = name.strip_prefix("weak") {
-        if let Ok(n) = num.parse::<u32>() {
-            if n > 10 { return None; }
-            return Some(Box::new(weak::WeakBot::new(n as f64 / 10.)));
-        }
-        None
-    }
-    else if let Some(num) = name.strip_prefix("basic") {
-        if let Ok(n) = num.parse::<u32>() {
-            if n == 0 { return None; }
-            return Some(Box::new(basic::BasicBot::new(n)));
+    parse_bot(name).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bot_strings() {
+        for name in [
+            "random", "weak5", "basic3-region", "adapt4-mobility+0.5*distance",
+            "time500ms", "time1.5s-region", "basic4@0", "time2s-region@64",
+            "mcts1000", "mctsr200", "mcts50ms", "basic5:2", "time1s-region@64:4",
+        ] {
+            assert!(map_bot_string(name).is_some(), "{}", name);
         }
-        None
-    }
-    else if let Some(num) = name.strip_prefix("adapt") {
-        if let Ok(n) = num.parse::<u32>() {
-            if n == 0 { return None; }
-            return Some(Box::new(adapt::AdaptiveBot::new(2_u64.pow(n+4))));
+        for name in [
+            "weak11", "basic0", "random-region", "basic3-space", "time0ms", "time5", "timefast",
+            "random@4", "weak5@4", "basic3@", "basic3@big",
+            "mcts0", "mctsr", "mcts100-region", "mcts100@4",
+            "basic5:0", "basic5:", "time1s:x", "adapt4:2", "mcts100:2", "random:2", "weak5:2",
+        ] {
+            assert!(map_bot_string(name).is_none(), "{}", name);
         }
-        None
     }
-    else { None }
-}
\ No newline at end of file
+}
@@ -1,150 +1,49 @@
-use std::cmp::min;
-use rand::seq::SliceRandom;
-use rand::{thread_rng, Rng};
-
-use crate::bot::base::Bot;
+use std::sync::Arc;
+use crate::bot::base::{Bot, SearchInfo};
+use crate::bot::eval::{Evaluator, Mobility};
+use crate::bot::search::{NodeBudget, Searcher};
+use crate::bot::tt::GameTable;
 use crate::qd::state::{GameState};
-use crate::qd::legalcomp::{get_possible_attack_mask, get_possible_legal_moves};
-
-const INFINITY: f64 = 1e6;
 
+/// Alpha-beta over a node budget, so forcing lines get searched deeper.
 #[derive(Clone)]
 pub struct AdaptiveBot {
-    max_compute: u64
+    max_compute: u64,
+    evaluator: Arc<dyn Evaluator>,
+    table: GameTable,
 }
 
-fn queens_in_reach(state: &GameState) -> bool {
-    if (get_possible_attack_mask(state.wqueen) | get_possible_attack_mask(state.bqueen))
-    & (1u64 << state.wqueen | 1u64 << state.bqueen) != 0 {
-        if get_possible_legal_moves(state) & (1u64 << state.wqueen | 1u64 << state.bqueen) != 0 { return true; }
+impl AdaptiveBot {
+    pub fn new(max_compute: u64) -> Self {
+        Self { max_compute, evaluator: Arc::new(Mobility), table: GameTable::default() }
     }
-    false
-}
-
-fn use_heuristic(state: &GameState) -> bool {
-    if state.result().is_some() { return true; }
-    if queens_in_reach(state) { return true; }
-    false
-}
 
-fn heuristic(state: &GameState) -> f64 {
-    if state.result() == Some(true) {
-        return INFINITY;
-    }
-    if state.result() == Some(false) {
-        return -INFINITY;
+    pub fn evaluator(mut self, evaluator: Arc<dyn Evaluator>) -> Self {
+        self.evaluator = evaluator;
+        self
     }
-    if queens_in_reach(state) {
-        if state.is_white_turn { return INFINITY; } else { return -INFINITY; }
-    }
-    let white_state = GameState::new(
-        Some(state.wqueen),
-        Some(state.bqueen),
-        Some(state.blocks),
-        Some(true),
-    );
-    let black_state = GameState::new(
-        Some(state.wqueen),
-        Some(state.bqueen),
-        Some(state.blocks),
-        Some(false),
-    );
-    let white_score = get_possible_legal_moves(&white_state).count_ones();
-    let black_score = get_possible_legal_moves(&black_state).count_ones();
-    white_score as f64 - black_score as f64
-}
 
-fn get_children(state: &GameState) -> Vec<(GameState, u8)> {
-    let legal_moves = get_possible_legal_moves(state);
-    let mut children = Vec::new();
-    for i in 0..64 {
-        if (legal_moves >> i) & 1 == 1 {
-            let mut child_state = state.clone();
-            child_state.make_move(i as u8);
-            children.push((child_state, i as u8));
-        }
+    /// Transposition table size in megabytes, 0 for none.
+    pub fn tt_size(mut self, tt_mb: usize) -> Self {
+        self.table = GameTable::new(tt_mb);
+        self
     }
-    let mut rng = thread_rng();
-    children.shuffle(&mut rng);
-    children
 }
 
-fn minimax_local(
-    state: &GameState, 
-    max_compute: u64, 
-    alpha: f64, beta: f64,
-    top_level: bool
-) -> (f64, Option<u8>, u64, bool) {
-    if (max_compute == 0 || use_heuristic(state)) && !top_level {
-        return (heuristic(state), None, max_compute, false);
-    }
-
-    let mut alpha = alpha;
-    let mut beta = beta;
-
-    let mut best_value = if state.is_white_turn { -INFINITY } else { INFINITY };
-    let mut best_move = None;
-    let mut best_move_unpruned = None;
-    let mut pruned = false;
-
-    let mut remaining = max_compute;
-
-    let mut children = get_children(state);
-
-    while let Some((child, move_made)) = children.pop() {
-        let max_cost = min(remaining / (children.len() as u64 + 1) * 5 / 4, remaining);
-        let (mut value, _, cost, eval_pruned) 
-            = minimax_local(&child, max_cost, alpha, beta, false);
-        if value > 0. { value -= 0.01 } else { value += 0.01 }
-        remaining -= cost;
-        if state.is_white_turn {
-            if value >= best_value {
-                best_value = value;
-                if !eval_pruned { best_move = Some(move_made) };
-                best_move_unpruned = Some(move_made);
-            }
-            alpha = alpha.max(value);
-        } else {
-            if value <= best_value {
-                best_value = value;
-                if !eval_pruned { best_move = Some(move_made) };
-                best_move_unpruned = Some(move_made);
-            }
-            beta = beta.min(value);
-        }
-        if beta <= alpha {
-            pruned = true;
-            break;
-        }
+impl Bot for AdaptiveBot {
+    fn decide(&self, state: GameState) -> u8 {
+        self.decide_with_info(state).best_move
     }
 
-    if best_move.is_none() {
-        best_move = best_move_unpruned;
+    fn new_game(&mut self, _start: GameState) {
+        self.table.new_game();
     }
 
-    let mut rng = thread_rng();
-    let noise: f64 = rng.gen_range(-0.001..0.001);
-    best_value += noise;
-
-    (best_value, best_move, max_compute - remaining, pruned)
-}
-
-fn minimax(state: &GameState, max_compute: u64) -> (f64, Option<u8>, u64) {
-    let (best_value, best_move, remaining, _) = 
-        minimax_local(state, max_compute, -INFINITY, INFINITY, true);
-    (best_value, best_move, remaining)
-}
-
-impl AdaptiveBot {
-    pub fn new(max_compute: u64) -> Self {
-        Self { max_compute }
+    fn decide_with_info(&self, state: GameState) -> SearchInfo {
+        let tt = self.table.get();
+        Searcher::new(self.evaluator.clone(), NodeBudget(self.max_compute))
+            .shuffle_moves(true)
+            .tt(tt)
+            .best_move_info(&state)
     }
 }
-
-impl Bot for AdaptiveBot {
-    fn decide(&self, state: GameState) -> u8 {
-        let (_, best_move, _) = minimax(&state, self.max_compute);
-        // println!("dbg {} {:.3}", a, (b as f64) / (self.max_compute as f64));
-        best_move.unwrap()
-    }
-}
\ No newline at end of file
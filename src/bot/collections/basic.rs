@@ -1,144 +1,81 @@
-use rand::{thread_rng, Rng};
-use crate::bot::base::Bot;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::bot::base::{Bot, SearchInfo};
+use crate::bot::eval::{Evaluator, Mobility};
+use crate::bot::search::{DepthLimit, Searcher};
+use crate::bot::tt::GameTable;
 use crate::qd::state::{GameState};
-use crate::qd::legalcomp::{get_possible_attack_mask, get_possible_legal_moves};
-
-const INFINITY: f64 = 1e6;
 
+/// Full-width alpha-beta to a fixed depth. With several threads it
+/// deepens to that depth instead, so the threads can share work.
 #[derive(Clone)]
 pub struct BasicBot {
     depth: u32,
+    evaluator: Arc<dyn Evaluator>,
+    table: GameTable,
+    threads: usize,
 }
 
-fn queens_in_reach(state: &GameState) -> bool {
-    if (get_possible_attack_mask(state.wqueen) | get_possible_attack_mask(state.bqueen))
-    & (1u64 << state.wqueen | 1u64 << state.bqueen) != 0 {
-        if get_possible_legal_moves(state) & (1u64 << state.wqueen | 1u64 << state.bqueen) != 0 { return true; }
+impl BasicBot {
+    pub fn new(depth: u32) -> Self {
+        Self { depth, evaluator: Arc::new(Mobility), table: GameTable::default(), threads: 1 }
     }
-    false
-}
 
-fn use_heuristic(state: &GameState) -> bool {
-    if state.result().is_some() { return true; }
-    if queens_in_reach(state) { return true; }
-    false
-}
-
-fn heuristic(state: &GameState) -> f64 {
-    if state.result() == Some(true) {
-        return INFINITY;
-    }
-    if state.result() == Some(false) {
-        return -INFINITY;
+    pub fn evaluator(mut self, evaluator: Arc<dyn Evaluator>) -> Self {
+        self.evaluator = evaluator;
+        self
     }
-    if queens_in_reach(state) {
-        if state.is_white_turn { return INFINITY; } else { return -INFINITY; }
+
+    /// Transposition table size in megabytes, 0 for none.
+    pub fn tt_size(mut self, tt_mb: usize) -> Self {
+        self.table = GameTable::new(tt_mb);
+        self
     }
-    let white_state = GameState::new(
-        Some(state.wqueen),
-        Some(state.bqueen),
-        Some(state.blocks),
-        Some(true),
-    );
-    let black_state = GameState::new(
-        Some(state.wqueen),
-        Some(state.bqueen),
-        Some(state.blocks),
-        Some(false),
-    );
-    let white_score = get_possible_legal_moves(&white_state).count_ones();
-    let black_score = get_possible_legal_moves(&black_state).count_ones();
-    white_score as f64 - black_score as f64
-}
 
-fn get_children(state: &GameState) -> Vec<(GameState, u8)> {
-    let legal_moves = get_possible_legal_moves(state);
-    let mut children = Vec::new();
-    for i in 0..64 {
-        if (legal_moves >> i) & 1 == 1 {
-            let mut child_state = state.clone();
-            child_state.make_move(i as u8);
-            children.push((child_state, i as u8));
-        }
+    /// Threads searching each move; see `Searcher::deepen_parallel`.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
     }
-    children
 }
 
-fn minimax_local(
-    state: &GameState, 
-    depth: u32, 
-    alpha: f64, beta: f64,
-    ab_pruning: bool,
-    top_level: bool
-) -> (f64, Option<u8>, bool) {
-    if (depth == 0 || use_heuristic(state)) && !top_level {
-        // println!("{}: dbgg {}", depth, heuristic(state));
-        return (heuristic(state), None, false);
+impl Bot for BasicBot {
+    fn decide(&self, state: GameState) -> u8 {
+        self.decide_with_info(state).best_move
     }
 
-    assert!((!top_level) || depth > 0);
-
-    let mut alpha = alpha;
-    let mut beta = beta;
-
-    let mut best_value = if state.is_white_turn { -INFINITY } else { INFINITY };
-    let mut best_move = None;
-    let mut best_move_unpruned = None;
-    let mut pruned = false;
+    fn new_game(&mut self, _start: GameState) {
+        self.table.new_game();
+    }
 
-    for (child, move_made) in get_children(state) {
-        let (mut value, _, eval_pruned) 
-            = minimax_local(&child, depth - 1, alpha, beta, ab_pruning, false);
-        if value > 0. { value -= 0.01 } else { value += 0.01 }
-        if state.is_white_turn {
-            if value >= best_value {
-                best_value = value;
-                if !eval_pruned { best_move = Some(move_made) };
-                best_move_unpruned = Some(move_made);
-            }
-            alpha = alpha.max(value);
-        } else {
-            if value <= best_value {
-                best_value = value;
-                if !eval_pruned { best_move = Some(move_made) };
-                best_move_unpruned = Some(move_made);
-            }
-            beta = beta.min(value);
+    fn decide_with_info(&self, state: GameState) -> SearchInfo {
+        let tt = self.table.get();
+        let searcher = Searcher::new(self.evaluator.clone(), DepthLimit(self.depth)).tt(tt);
+        if self.threads == 1 {
+            return searcher.best_move_info(&state);
         }
-        if beta <= alpha && ab_pruning {
-            pruned = true;
-            break;
+        let start = Instant::now();
+        if let Some(info) = searcher.solved_info(&state, start) {
+            return info;
         }
+        let no_deadline = start + Duration::from_secs(1 << 30);
+        let (result, depth) = searcher.deepen_parallel(&state, no_deadline, self.threads);
+        searcher.info(&state, &result, Some(depth), start.elapsed())
     }
-
-    if best_move.is_none() {
-        best_move = best_move_unpruned;
-    }
-
-    let mut rng = thread_rng();
-    let noise: f64 = rng.gen_range(-0.001..0.001);
-    best_value += noise;
-
-    (best_value, best_move, pruned)
-}
-
-fn minimax(state: &GameState, depth: u32) -> (f64, Option<u8>) {
-    let (best_value, best_move, _) = 
-        minimax_local(state, depth, -INFINITY, INFINITY, true, true);
-    (best_value, best_move)
 }
 
-impl BasicBot {
-    pub fn new(depth: u32) -> Self {
-        Self { depth: depth }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qd::error::QdError;
+    use crate::qd::legalcomp::get_possible_legal_moves;
+
+    #[test]
+    fn test_bad_input_is_an_error() {
+        let over = GameState::new(Some(0), Some(63), Some(1 << 1 | 1 << 8 | 1 << 9), Some(true));
+        assert_eq!(BasicBot::new(3).try_decide_with_info(over).err(), Some(QdError::GameOver));
+        let state = GameState::def();
+        let to = BasicBot::new(0).decide(state);
+        assert!(get_possible_legal_moves(&state) & 1 << to != 0);
     }
 }
-
-impl Bot for BasicBot {
-    fn decide(&self, state: GameState) -> u8 {
-        assert!(state.result().is_none());
-        assert!(self.depth > 0);
-        let (_, best_move) = minimax(&state, self.depth);
-        best_move.unwrap()
-    }
-}
\ No newline at end of file
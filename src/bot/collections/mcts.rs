@@ -0,0 +1,256 @@
+use std::time::{Duration, Instant};
+use crate::bot::base::{Bot, SearchInfo};
+use crate::bot::collections::random::random_set_bit_index;
+use crate::qd::legalcomp::get_possible_legal_moves;
+use crate::qd::magic::queen_attacks;
+use crate::qd::state::{GameState};
+
+/// How long an `MctsBot` thinks per move.
+#[derive(PartialEq)]
+#[derive(Debug, Eq)]
+#[derive(Copy, Clone)]
+pub enum MctsBudget {
+    Iterations(u64),
+    Time(Duration),
+}
+
+/// How an `MctsBot` plays out games from new leaves.
+#[derive(PartialEq)]
+#[derive(Debug, Eq)]
+#[derive(Copy, Clone)]
+pub enum Playout {
+    /// Uniformly random moves.
+    Random,
+    /// Takes the queen when it can, and otherwise prefers random squares
+    /// the other queen cannot reach.
+    Light,
+}
+
+impl Playout {
+    fn pick(self, state: &GameState, moves: u64) -> u8 {
+        let (own, other) = if state.is_white_turn() {
+            (state.wqueen(), state.bqueen())
+        } else {
+            (state.bqueen(), state.wqueen())
+        };
+        if self == Playout::Light {
+            if moves & 1 << other != 0 {
+                return other;
+            }
+            let reached = queen_attacks(other, state.blocks() | 1 << own);
+            let safe = moves & !reached;
+            if safe != 0 {
+                return random_set_bit_index(safe).unwrap() as u8;
+            }
+        }
+        random_set_bit_index(moves).unwrap() as u8
+    }
+
+    /// Plays `state` out to the end and returns whether white won.
+    fn run(self, mut state: GameState) -> bool {
+        loop {
+            if state.wqueen() == state.bqueen() {
+                return !state.is_white_turn();
+            }
+            let moves = get_possible_legal_moves(&state);
+            if moves == 0 {
+                return !state.is_white_turn();
+            }
+            state.make_move(self.pick(&state, moves));
+        }
+    }
+}
+
+struct Node {
+    state: GameState,
+    /// The move that led here.
+    to: u8,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    /// Moves without a child yet.
+    untried: u64,
+    visits: u32,
+    /// Playouts won by the side that moved into this node.
+    wins: u32,
+}
+
+impl Node {
+    fn new(state: GameState, to: u8, parent: Option<usize>) -> Self {
+        let untried = if state.result().is_some() { 0 } else { get_possible_legal_moves(&state) };
+        Self { state, to, parent, children: Vec::new(), untried, visits: 0, wins: 0 }
+    }
+}
+
+/// Monte Carlo tree search with UCT: grows a tree towards the moves whose
+/// playouts win most often, and plays the most visited root move.
+#[derive(Clone)]
+pub struct MctsBot {
+    budget: MctsBudget,
+    exploration: f64,
+    playout: Playout,
+}
+
+impl MctsBot {
+    pub fn new(budget: MctsBudget) -> Self {
+        Self { budget, exploration: std::f64::consts::SQRT_2, playout: Playout::Light }
+    }
+
+    /// The UCT exploration constant.
+    pub fn exploration(mut self, exploration: f64) -> Self {
+        self.exploration = exploration;
+        self
+    }
+
+    pub fn playout(mut self, playout: Playout) -> Self {
+        self.playout = playout;
+        self
+    }
+
+    fn select_child(&self, tree: &[Node], node: usize) -> usize {
+        let log_visits = (tree[node].visits as f64).ln();
+        let uct = |child: usize| {
+            let child = &tree[child];
+            let visits = child.visits as f64;
+            child.wins as f64 / visits + self.exploration * (log_visits / visits).sqrt()
+        };
+        *tree[node].children
+            .iter()
+            .max_by(|&&a, &&b| uct(a).total_cmp(&uct(b)))
+            .unwrap()
+    }
+
+    fn iterate(&self, tree: &mut Vec<Node>) {
+        let mut node = 0;
+        while tree[node].untried == 0 && !tree[node].children.is_empty() {
+            node = self.select_child(tree, node);
+        }
+        if tree[node].untried != 0 {
+            let to = random_set_bit_index(tree[node].untried).unwrap() as u8;
+            tree[node].untried &= !(1 << to);
+            let mut state = tree[node].state;
+            state.make_move(to);
+            tree.push(Node::new(state, to, Some(node)));
+            let child = tree.len() - 1;
+            tree[node].children.push(child);
+            node = child;
+        }
+        let white_won = self.playout.run(tree[node].state);
+        let mut current = Some(node);
+        while let Some(index) = current {
+            let node = &mut tree[index];
+            node.visits += 1;
+            if white_won != node.state.is_white_turn() {
+                node.wins += 1;
+            }
+            current = node.parent;
+        }
+    }
+}
+
+impl Bot for MctsBot {
+    fn decide(&self, state: GameState) -> u8 {
+        self.decide_with_info(state).best_move
+    }
+
+    /// Reports the playouts as nodes and the most visited line as the
+    /// principal variation. There is no score.
+    fn decide_with_info(&self, state: GameState) -> SearchInfo {
+        let start = Instant::now();
+        let mut tree = vec![Node::new(state, 0, None)];
+        match self.budget {
+            MctsBudget::Iterations(iterations) => {
+                for _ in 0..iterations.max(1) {
+                    self.iterate(&mut tree);
+                }
+            }
+            MctsBudget::Time(time) => {
+                let deadline = Instant::now() + time;
+                loop {
+                    self.iterate(&mut tree);
+                    if Instant::now() >= deadline {
+                        break;
+                    }
+                }
+            }
+        }
+        let most_visited = |node: usize| tree[node].children
+            .iter()
+            .copied()
+            .max_by_key(|&child| tree[child].visits);
+        let mut pv = Vec::new();
+        let mut node = 0;
+        while let Some(child) = most_visited(node) {
+            pv.push(tree[child].to);
+            node = child;
+        }
+        SearchInfo {
+            best_move: pv[0],
+            score: None,
+            pv,
+            depth: None,
+            nodes: tree[0].visits as u64,
+            time: start.elapsed(),
+            proven: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::collections::random::RandomBot;
+    use crate::bot::base::bots_fight;
+    use crate::qd::utils::vgs;
+    use crate::rng::seed_thread;
+
+    #[test]
+    fn test_takes_queen() {
+        let state = vgs("
+            ...B....
+            ........
+            ........
+            ........
+            ........
+            ........
+            ........
+            ...W....
+        ", true);
+        for playout in [Playout::Random, Playout::Light] {
+            let bot = MctsBot::new(MctsBudget::Iterations(500)).playout(playout);
+            let info = bot.decide_with_info(state);
+            assert_eq!(info.best_move, 59);
+            assert_eq!(info.pv, vec![59]);
+            assert_eq!(info.nodes, 500);
+        }
+    }
+
+    #[test]
+    fn test_beats_random() {
+        seed_thread(81);
+        let random = RandomBot::new();
+        for playout in [Playout::Random, Playout::Light] {
+            let bot = MctsBot::new(MctsBudget::Iterations(200)).playout(playout);
+            let wins = (0..10)
+                .filter(|&i| {
+                    let start = GameState::def_rand();
+                    if i % 2 == 0 {
+                        bots_fight(&bot, &random, start).result() == Some(true)
+                    } else {
+                        bots_fight(&random, &bot, start).result() == Some(false)
+                    }
+                })
+                .count();
+            assert!(wins >= 8, "{:?} won {}", playout, wins);
+        }
+    }
+
+    #[test]
+    fn test_time_budget() {
+        let bot = MctsBot::new(MctsBudget::Time(Duration::from_millis(20)));
+        let start = Instant::now();
+        let state = GameState::def();
+        let to = bot.decide(state);
+        assert!(get_possible_legal_moves(&state) & 1 << to != 0);
+        assert!(start.elapsed() < Duration::from_millis(500));
+    }
+}
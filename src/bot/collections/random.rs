@@ -2,17 +2,19 @@ use rand::Rng;
 use crate::bot::base::Bot;
 use crate::qd::state::GameState;
 use crate::qd::legalcomp::{get_possible_legal_moves};
+use crate::rng::with_rng;
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct RandomBot {}
 
-fn random_set_bit_index(bitboard: u64) -> Option<u32> {
+/// A uniformly random set bit of `bitboard`.
+pub(crate) fn random_set_bit_index(bitboard: u64) -> Option<u32> {
     let popcnt = bitboard.count_ones();
     if popcnt == 0 {
         return None;
     }
 
-    let k = rand::thread_rng().gen_range(0..popcnt);
+    let k = with_rng(|rng| rng.gen_range(0..popcnt));
 
     let mut n = bitboard;
     for _ in 0..k {
@@ -39,9 +41,7 @@ impl Bot for RandomBot {
         move_index.expect("No legal moves available");
         let move_index = move_index.unwrap();
 
-        let move_to = move_index as u8;
-
-        move_to
+        move_index as u8
     }
 }
 
@@ -55,7 +55,7 @@ mod tests {
             let mut state = GameState::def();
             let bot = RandomBot::new();
 
-            while let None = state.result() {
+            while state.result().is_none() {
                 let move_to = bot.decide(state);
                 state.make_move(move_to);
             }
@@ -0,0 +1,64 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::bot::base::{Bot, SearchInfo};
+use crate::bot::eval::{Evaluator, Mobility};
+use crate::bot::search::{DepthLimit, Searcher, MAX_DEPTH};
+use crate::bot::tt::GameTable;
+use crate::qd::state::{GameState};
+
+/// Iterative-deepening alpha-beta that thinks for a fixed time per move.
+/// It always finishes depth 1, so it may run over on very short budgets.
+#[derive(Clone)]
+pub struct TimedBot {
+    time: Duration,
+    evaluator: Arc<dyn Evaluator>,
+    table: GameTable,
+    threads: usize,
+}
+
+impl TimedBot {
+    pub fn new(time: Duration) -> Self {
+        Self { time, evaluator: Arc::new(Mobility), table: GameTable::default(), threads: 1 }
+    }
+
+    pub fn evaluator(mut self, evaluator: Arc<dyn Evaluator>) -> Self {
+        self.evaluator = evaluator;
+        self
+    }
+
+    /// Transposition table size in megabytes, 0 for none.
+    pub fn tt_size(mut self, tt_mb: usize) -> Self {
+        self.table = GameTable::new(tt_mb);
+        self
+    }
+
+    /// Threads searching each move; see `Searcher::deepen_parallel`.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+}
+
+impl Bot for TimedBot {
+    fn decide(&self, state: GameState) -> u8 {
+        self.decide_with_info(state).best_move
+    }
+
+    fn new_game(&mut self, _start: GameState) {
+        self.table.new_game();
+    }
+
+    fn decide_with_info(&self, state: GameState) -> SearchInfo {
+        let start = Instant::now();
+        let deadline = start + self.time;
+        let tt = self.table.get();
+        let searcher = Searcher::new(self.evaluator.clone(), DepthLimit(MAX_DEPTH)).tt(tt);
+        // The exact solver gets half the time, leaving the rest for at
+        // least the depth-1 search.
+        if let Some(info) = searcher.solved_info_by(&state, start, start + self.time / 2) {
+            return info;
+        }
+        let (result, depth) = searcher.deepen_parallel(&state, deadline, self.threads);
+        searcher.info(&state, &result, Some(depth), start.elapsed())
+    }
+}
@@ -1,134 +1,47 @@
-use rand::{thread_rng, Rng};
-use crate::bot::base::Bot;
+use std::sync::Arc;
+use std::time::Instant;
+use rand::Rng;
+use crate::bot::base::{Bot, SearchInfo};
+use crate::bot::eval::{Evaluator, Mobility};
+use crate::bot::search::{DepthLimit, Searcher};
+use crate::rng::with_rng;
 use crate::qd::state::{GameState};
-use crate::qd::legalcomp::{get_possible_legal_moves};
-
-const INFINITY: f64 = 1e6;
 
+/// Picks between the best moves at depth 1 and depth 2, leaning towards
+/// depth 2 as `level` goes up.
 #[derive(Clone)]
 pub struct WeakBot {
     level: f64,
-}
-
-fn use_heuristic(state: &GameState) -> bool {
-    if state.result().is_some() { return true; }
-    false
-}
-
-fn heuristic(state: &GameState) -> f64 {
-    if state.result() == Some(true) {
-        return INFINITY;
-    }
-    if state.result() == Some(false) {
-        return -INFINITY;
-    }
-    let white_state = GameState::new(
-        Some(state.wqueen),
-        Some(state.bqueen),
-        Some(state.blocks),
-        Some(true),
-    );
-    let black_state = GameState::new(
-        Some(state.wqueen),
-        Some(state.bqueen),
-        Some(state.blocks),
-        Some(false),
-    );
-    let white_score = get_possible_legal_moves(&white_state).count_ones();
-    let black_score = get_possible_legal_moves(&black_state).count_ones();
-    white_score as f64 - black_score as f64
-}
-
-fn get_children(state: &GameState) -> Vec<(GameState, u8)> {
-    let legal_moves = get_possible_legal_moves(state);
-    let mut children = Vec::new();
-    for i in 0..64 {
-        if (legal_moves >> i) & 1 == 1 {
-            let mut child_state = state.clone();
-            child_state.make_move(i as u8);
-            children.push((child_state, i as u8));
-        }
-    }
-    children
-}
-
-fn minimax_local(
-    state: &GameState, 
-    depth: u32, 
-    alpha: f64, beta: f64,
-    ab_pruning: bool,
-    top_level: bool
-) -> (f64, Option<u8>, bool) {
-    if (depth == 0 || use_heuristic(state)) && !top_level {
-        return (heuristic(state), None, false);
-    }
-
-    assert!((!top_level) || depth > 0);
-
-    let mut alpha = alpha;
-    let mut beta = beta;
-
-    let mut best_value = if state.is_white_turn { -INFINITY } else { INFINITY };
-    let mut best_move = None;
-    let mut best_move_unpruned = None;
-    let mut pruned = false;
-
-    for (child, move_made) in get_children(state) {
-        let (mut value, _, eval_pruned) 
-            = minimax_local(&child, depth - 1, alpha, beta, ab_pruning, false);
-        if value > 0. { value -= 0.01 } else { value += 0.01 }
-        if state.is_white_turn {
-            if value >= best_value {
-                best_value = value;
-                if !eval_pruned { best_move = Some(move_made) };
-                best_move_unpruned = Some(move_made);
-            }
-            alpha = alpha.max(value);
-        } else {
-            if value <= best_value {
-                best_value = value;
-                if !eval_pruned { best_move = Some(move_made) };
-                best_move_unpruned = Some(move_made);
-            }
-            beta = beta.min(value);
-        }
-        if beta <= alpha && ab_pruning {
-            pruned = true;
-            break;
-        }
-    }
-
-    if best_move.is_none() {
-        best_move = best_move_unpruned;
-    }
-
-    let mut rng = thread_rng();
-    let noise: f64 = rng.gen_range(-0.001..0.001);
-    best_value += noise;
-
-    (best_value, best_move, pruned)
-}
-
-fn minimax(state: &GameState, depth: u32) -> (f64, Option<u8>) {
-    let (best_value, best_move, _) = 
-        minimax_local(state, depth, -INFINITY, INFINITY, true, true);
-    (best_value, best_move)
+    evaluator: Arc<dyn Evaluator>,
 }
 
 impl WeakBot {
     pub fn new(level: f64) -> Self {
         assert!(0. <= level);
         assert!(level <= 1.);
-        Self { level }
+        Self { level, evaluator: Arc::new(Mobility) }
+    }
+
+    pub fn evaluator(mut self, evaluator: Arc<dyn Evaluator>) -> Self {
+        self.evaluator = evaluator;
+        self
     }
 }
 
 impl Bot for WeakBot {
     fn decide(&self, state: GameState) -> u8 {
-        let (_, best_move_1) = minimax(&state, 1);
-        let (_, best_move_2) = minimax(&state, 2);
-        let mut rng = thread_rng();
-        let choose: bool = rng.gen_bool(self.level.sqrt());
-        if choose { best_move_2.unwrap() } else { best_move_1.unwrap() }
+        self.decide_with_info(state).best_move
     }
-}
\ No newline at end of file
+
+    fn decide_with_info(&self, state: GameState) -> SearchInfo {
+        let start = Instant::now();
+        let searcher = |depth| Searcher::new(self.evaluator.clone(), DepthLimit(depth)).solve_endings(false);
+        let result_1 = searcher(1).search(&state);
+        let result_2 = searcher(2).search(&state);
+        let choose: bool = with_rng(|rng| rng.gen_bool(self.level.sqrt()));
+        let (depth, result) = if choose { (2, result_2) } else { (1, result_1) };
+        let mut info = searcher(depth).info(&state, &result, Some(depth), start.elapsed());
+        info.nodes = result_1.nodes + result_2.nodes;
+        info
+    }
+}
@@ -6,8 +6,19 @@ use rayon::prelude::*;
 use rayon::ThreadPoolBuilder;
 use crate::bot::base::{
     Bot,
-    bots_fight_rand
+    bots_fight_rand,
+    bots_fight_rand_game
 };
+use crate::qd::game::Game;
+use crate::qd::startpos::{StartPosError, StartPositionGenerator};
+use crate::rng::{next_seed, rng_from_seed, seed_thread, with_rng, QdRng};
+
+/// A tournament game, with the bots given as indices into the bot list.
+pub struct PlayedGame {
+    pub white: usize,
+    pub black: usize,
+    pub game: Game,
+}
 
 fn expected_score(r1: f64, r2: f64) -> f64 {
     1.0 / (1.0 + 10f64.powf((r2 - r1) / 400.0))
@@ -28,14 +39,60 @@ fn get_computed_k(
     k_start * (k_end / k_start).powf(progress)
 }
 
-pub fn run_tournament<'a>(
+/// Settings for `run_tournament` and `run_benchmark`.
+pub struct TournamentOptions<'a> {
+    pub num_matchups: usize,
+    /// The K-factor of the first game. It moves geometrically towards
+    /// `k_end` over the run.
+    pub k_start: f64,
+    pub k_end: f64,
+    pub num_threads: usize,
+    /// Draws the start of every game.
+    pub start_gen: StartPositionGenerator,
+    /// Called with the number of games played since the last call.
+    pub progress: Option<Box<dyn Fn(usize) + 'a>>,
+    /// Makes the games and ratings reproducible for a fixed `num_threads`.
+    pub seed: Option<u64>,
+}
+
+impl Default for TournamentOptions<'_> {
+    fn default() -> Self {
+        Self {
+            num_matchups: 100,
+            k_start: 32.,
+            k_end: 32.,
+            num_threads: 1,
+            start_gen: StartPositionGenerator::default(),
+            progress: None,
+            seed: None,
+        }
+    }
+}
+
+/// Runs `num_matchups` rated games between randomly paired bots and
+/// returns their ratings. Fails if `start_gen` cannot produce a start.
+pub fn run_tournament(
     bots: Vec<Box<dyn Bot>>,
-    num_matchups: usize,
-    k_start: f64,
-    k_end: f64,
-    num_threads: usize,
-    prog_func: &Option<Box<dyn Fn(usize) + 'a>>
-) -> Vec<f64> {
+    options: &TournamentOptions,
+) -> Result<Vec<f64>, StartPosError> {
+    tournament(bots, options, None)
+}
+
+/// Like `run_tournament`, but also appends every game played to `games`.
+pub fn run_tournament_recorded(
+    bots: Vec<Box<dyn Bot>>,
+    options: &TournamentOptions,
+    games: &mut Vec<PlayedGame>,
+) -> Result<Vec<f64>, StartPosError> {
+    tournament(bots, options, Some(games))
+}
+
+fn tournament(
+    bots: Vec<Box<dyn Bot>>,
+    options: &TournamentOptions,
+    mut games: Option<&mut Vec<PlayedGame>>,
+) -> Result<Vec<f64>, StartPosError> {
+    let TournamentOptions { num_matchups, k_start, k_end, num_threads, ref start_gen, progress: ref prog_func, seed } = *options;
     let bots = Arc::new(bots);
     let mut elos = vec![0.0f64; bots.len()];
     let pool = ThreadPoolBuilder::new()
@@ -44,8 +101,8 @@ pub fn run_tournament<'a>(
         .unwrap();
     let mut remaining = num_matchups;
 
-    let matchup_func = |elos: &Vec<f64>, k: f64| {
-        let mut rng = rand::thread_rng();
+    let mut rng = rng_from_seed(seed);
+    let matchup_func = |elos: &Vec<f64>, k: f64, rng: &mut QdRng| {
         let i = rng.gen_range(0..bots.len());
         let mut chosen_bots = (0..bots.len())
             .filter(
@@ -56,36 +113,48 @@ pub fn run_tournament<'a>(
                 }
             )
             .collect::<Vec<_>>();
-        if chosen_bots.len() == 0 {
+        if chosen_bots.is_empty() {
             chosen_bots = (0..bots.len())
                 .filter(|j| *j != i)
                 .collect::<Vec<_>>();
         }
         let j_chosen = rng.gen_range(0..chosen_bots.len());
         let j = chosen_bots[j_chosen];
-        (i, j)
+        (i, j, next_seed(rng))
     };
 
     while remaining > 0 {
         let progress = 1.0 - remaining as f64 / num_matchups as f64;
         let k = get_computed_k(k_start, k_end, progress);
-        let mut inp = vec![(0usize, 0usize); 0];
+        let mut inp = vec![(0usize, 0usize, 0u64); 0];
         let mut remaining_batch = num_threads * 4;
         while remaining_batch > 0 && remaining > 0 {
-            inp.push(matchup_func(&elos, k));
+            inp.push(matchup_func(&elos, k, &mut rng));
             remaining_batch -= 1;
             remaining -= 1;
         };
+        let record = games.is_some();
         let out = pool.install(|| {
             inp.into_par_iter()
-                .map(|(i, j)| {
+                .map(|(i, j, game_seed)| {
                     let b1 = &bots[i];
                     let b2 = &bots[j];
-                    (i, j, bots_fight_rand(b1.as_ref(), b2.as_ref()))
+                    seed_thread(game_seed);
+                    let start = with_rng(|rng| start_gen.try_generate_with(rng))?;
+                    if record {
+                        let (does_b1_win, b1_white, game) = bots_fight_rand_game(b1.as_ref(), b2.as_ref(), start);
+                        let (white, black) = if b1_white { (i, j) } else { (j, i) };
+                        Ok((i, j, does_b1_win, Some(PlayedGame { white, black, game })))
+                    } else {
+                        Ok((i, j, bots_fight_rand(b1.as_ref(), b2.as_ref(), start), None))
+                    }
                 })
-                .collect::<Vec<_>>()
-        });
-        for (i, j, does_b1_win) in out {
+                .collect::<Result<Vec<_>, StartPosError>>()
+        })?;
+        for (i, j, does_b1_win, played) in out {
+            if let (Some(games), Some(played)) = (games.as_mut(), played) {
+                games.push(played);
+            }
             let progress = 1.0 - remaining as f64 / num_matchups as f64;
             let k = get_computed_k(k_start, k_end, progress);
 
@@ -104,24 +173,23 @@ pub fn run_tournament<'a>(
             elos[i] = b1_new;
             elos[j] = b2_new;
         }
-        if prog_func.is_some() {
-            prog_func.as_ref().unwrap()(num_threads * 4);
+        if let Some(prog_func) = prog_func {
+            prog_func(num_threads * 4);
         }
     }
 
-    elos
+    Ok(elos)
 }
 
-pub fn run_benchmark<'a>(
+/// Rates `bot` by playing it against `oppo_bots`, whose ratings
+/// `oppo_elos` stay fixed. Fails if `start_gen` cannot produce a start.
+pub fn run_benchmark(
     bot: Box<dyn Bot>,
     oppo_bots: Vec<Box<dyn Bot>>,
-    num_matchups: usize,
     oppo_elos: Vec<f64>,
-    k_start: f64,
-    k_end: f64,
-    num_threads: usize,
-    prog_func: &Option<Box<dyn Fn(usize) + 'a>>
-) -> f64 {
+    options: &TournamentOptions,
+) -> Result<f64, StartPosError> {
+    let TournamentOptions { num_matchups, k_start, k_end, num_threads, ref start_gen, progress: ref prog_func, seed } = *options;
     let oppo_bots = Arc::new(oppo_bots);
     let oppo_elos = Arc::new(oppo_elos);
     let mut elo = 0.0f64;
@@ -131,39 +199,40 @@ pub fn run_benchmark<'a>(
         .unwrap();
     let mut remaining = num_matchups;
 
-    let matchup_func = |elo: f64, k: f64| {
-        let mut rng = rand::thread_rng();
+    let mut rng = rng_from_seed(seed);
+    let matchup_func = |elo: f64, k: f64, rng: &mut QdRng| {
         let mut chosen_bots = (0..oppo_bots.len())
             .filter(
                 |j| matchable_elos(elo, oppo_elos[*j], k)
             )
             .collect::<Vec<_>>();
-        if chosen_bots.len() == 0 {
+        if chosen_bots.is_empty() {
             chosen_bots = (0..oppo_bots.len()).collect::<Vec<_>>();
         }
         let j_chosen = rng.gen_range(0..chosen_bots.len());
-        let j = chosen_bots[j_chosen];
-        j
+        (chosen_bots[j_chosen], next_seed(rng))
     };
 
     while remaining > 0 {
         let progress = 1.0 - remaining as f64 / num_matchups as f64;
         let k = get_computed_k(k_start, k_end, progress);
-        let mut inp = vec![0usize; 0];
+        let mut inp = vec![(0usize, 0u64); 0];
         let mut remaining_batch = num_threads * 4;
         while remaining_batch > 0 && remaining > 0 {
-            inp.push(matchup_func(elo, k));
+            inp.push(matchup_func(elo, k, &mut rng));
             remaining_batch -= 1;
             remaining -= 1;
         };
         let out = pool.install(|| {
             inp.into_par_iter()
-                .map(|j| {
+                .map(|(j, game_seed)| {
                     let b2 = &oppo_bots[j];
-                    (j, bots_fight_rand(bot.as_ref(), b2.as_ref()))
+                    seed_thread(game_seed);
+                    let start = with_rng(|rng| start_gen.try_generate_with(rng))?;
+                    Ok((j, bots_fight_rand(bot.as_ref(), b2.as_ref(), start)))
                 })
-                .collect::<Vec<_>>()
-        });
+                .collect::<Result<Vec<_>, StartPosError>>()
+        })?;
         for (j, does_bot_win) in out {
             let progress = 1.0 - remaining as f64 / num_matchups as f64;
             let k = get_computed_k(k_start, k_end, progress);
@@ -173,10 +242,79 @@ pub fn run_benchmark<'a>(
             let bot_new = elo + k * (bot_score - bot_win_prob);
             elo = bot_new;
         }
-        if prog_func.is_some() {
-            prog_func.as_ref().unwrap()(num_threads * 4);
+        if let Some(prog_func) = prog_func {
+            prog_func(num_threads * 4);
         }
     };
 
-    elo
-}
\ No newline at end of file
+    Ok(elo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::collections::map_bot_string;
+
+    fn bots() -> Vec<Box<dyn Bot>> {
+        ["random", "weak5", "basic1"]
+            .iter()
+            .map(|name| map_bot_string(name).unwrap())
+            .collect()
+    }
+
+    type GameSummary = (usize, usize, Vec<u8>);
+
+    fn seeded_run(seed: u64, num_threads: usize) -> (Vec<f64>, Vec<GameSummary>) {
+        let mut games = Vec::new();
+        let options = TournamentOptions {
+            num_matchups: 40,
+            k_end: 16.,
+            num_threads,
+            seed: Some(seed),
+            ..TournamentOptions::default()
+        };
+        let elos = run_tournament_recorded(bots(), &options, &mut games).unwrap();
+        let games = games
+            .into_iter()
+            .map(|played| (played.white, played.black, played.game.moves().iter().map(|mv| mv.to).collect()))
+            .collect();
+        (elos, games)
+    }
+
+    #[test]
+    fn test_seeded_tournament_reproducible() {
+        assert_eq!(seeded_run(11, 1), seeded_run(11, 1));
+        assert_eq!(seeded_run(11, 3), seeded_run(11, 3));
+        assert_ne!(seeded_run(11, 1).1, seeded_run(12, 1).1);
+    }
+
+    #[test]
+    fn test_seeded_benchmark_reproducible() {
+        let run = |seed| {
+            let options = TournamentOptions {
+                num_matchups: 40,
+                k_end: 16.,
+                num_threads: 2,
+                seed: Some(seed),
+                ..TournamentOptions::default()
+            };
+            run_benchmark(map_bot_string("basic1").unwrap(), bots(), vec![0., 300., 600.], &options).unwrap()
+        };
+        assert_eq!(run(5), run(5));
+    }
+
+    #[test]
+    fn test_bad_start_gen_is_an_error() {
+        let options = TournamentOptions {
+            num_matchups: 8,
+            num_threads: 2,
+            start_gen: "white=d1,black=d8,blocks=0,reject=1".parse().unwrap(),
+            ..TournamentOptions::default()
+        };
+        assert_eq!(run_tournament(bots(), &options), Err(StartPosError::NoAcceptablePosition));
+        assert_eq!(
+            run_benchmark(map_bot_string("basic1").unwrap(), bots(), vec![0., 300., 600.], &options),
+            Err(StartPosError::NoAcceptablePosition)
+        );
+    }
+}
@@ -0,0 +1,292 @@
+//! Static evaluation of positions for the minimax bots.
+//!
+//! Evaluators can be picked by name, and combined linearly with specs such
+//! as `mobility+0.5*region`.
+
+use std::fmt;
+use std::sync::Arc;
+use crate::qd::legalcomp::get_possible_legal_moves_info;
+use crate::qd::magic::queen_attacks;
+use crate::qd::state::GameState;
+
+/// Scores positions where a search stops.
+pub trait Evaluator: Send + Sync {
+    /// Score of `state` from white's point of view. Finished games are
+    /// scored by the search itself and never reach the evaluator.
+    fn evaluate(&self, state: &GameState) -> f64;
+}
+
+impl<E: Evaluator + ?Sized> Evaluator for Arc<E> {
+    fn evaluate(&self, state: &GameState) -> f64 {
+        (**self).evaluate(state)
+    }
+}
+
+fn empty_squares(state: &GameState) -> u64 {
+    !state.blocks() & !(1u64 << state.wqueen()) & !(1u64 << state.bqueen())
+}
+
+/// The empty squares `queen` reaches in exactly one, two, three, ... queen
+/// moves, ignoring that every move blocks the square it leaves.
+pub fn distance_layers(queen: u8, empty: u64) -> Vec<u64> {
+    let mut layers = Vec::new();
+    let mut seen = 1u64 << queen;
+    let mut frontier = 1u64 << queen;
+    while frontier != 0 {
+        let mut next = 0;
+        let mut rest = frontier;
+        while rest != 0 {
+            let square = rest.trailing_zeros() as u8;
+            rest &= rest - 1;
+            next |= queen_attacks(square, !empty);
+        }
+        next &= empty & !seen;
+        if next == 0 {
+            break;
+        }
+        seen |= next;
+        layers.push(next);
+        frontier = next;
+    }
+    layers
+}
+
+/// Difference between the number of moves white and black have.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Mobility;
+
+impl Evaluator for Mobility {
+    fn evaluate(&self, state: &GameState) -> f64 {
+        let white_score = get_possible_legal_moves_info(state.wqueen(), state.bqueen(), state.blocks()).count_ones();
+        let black_score = get_possible_legal_moves_info(state.bqueen(), state.wqueen(), state.blocks()).count_ones();
+        white_score as f64 - black_score as f64
+    }
+}
+
+/// Territory: the empty squares white reaches in fewer queen moves than
+/// black, minus those black reaches first. Ties count for neither.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Region;
+
+impl Evaluator for Region {
+    fn evaluate(&self, state: &GameState) -> f64 {
+        let empty = empty_squares(state);
+        let white = distance_layers(state.wqueen(), empty);
+        let black = distance_layers(state.bqueen(), empty);
+        let mut white_seen = 0u64;
+        let mut black_seen = 0u64;
+        let mut score = 0i32;
+        for i in 0..white.len().max(black.len()) {
+            let white_layer = white.get(i).copied().unwrap_or(0);
+            let black_layer = black.get(i).copied().unwrap_or(0);
+            score += (white_layer & !black_seen & !black_layer).count_ones() as i32;
+            score -= (black_layer & !white_seen & !white_layer).count_ones() as i32;
+            white_seen |= white_layer;
+            black_seen |= black_layer;
+        }
+        score as f64
+    }
+}
+
+/// Mobility that also counts squares further away: a square `d` queen
+/// moves away is worth `1 / 2^(d - 1)`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct DistanceMobility;
+
+impl DistanceMobility {
+    fn side(queen: u8, empty: u64) -> f64 {
+        distance_layers(queen, empty)
+            .iter()
+            .enumerate()
+            .map(|(i, layer)| layer.count_ones() as f64 * 0.5f64.powi(i as i32))
+            .sum()
+    }
+}
+
+impl Evaluator for DistanceMobility {
+    fn evaluate(&self, state: &GameState) -> f64 {
+        let empty = empty_squares(state);
+        Self::side(state.wqueen(), empty) - Self::side(state.bqueen(), empty)
+    }
+}
+
+/// A weighted sum of other evaluators.
+#[derive(Clone, Default)]
+pub struct Weighted {
+    pub terms: Vec<(f64, Arc<dyn Evaluator>)>,
+}
+
+impl Evaluator for Weighted {
+    fn evaluate(&self, state: &GameState) -> f64 {
+        self.terms.iter().map(|(weight, evaluator)| weight * evaluator.evaluate(state)).sum()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvalError {
+    Empty,
+    UnknownEvaluator(String),
+    BadWeight(String),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::Empty => write!(f, "empty evaluator"),
+            EvalError::UnknownEvaluator(name) => write!(
+                f, "unknown evaluator \"{}\", expected one of: {}", name, EVALUATOR_NAMES.join(", ")
+            ),
+            EvalError::BadWeight(weight) => write!(f, "invalid weight \"{}\"", weight),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+pub const EVALUATOR_NAMES: [&str; 3] = ["mobility", "region", "distance"];
+
+fn evaluator_by_name(name: &str) -> Result<Arc<dyn Evaluator>, EvalError> {
+    match name {
+        "mobility" => Ok(Arc::new(Mobility)),
+        "region" => Ok(Arc::new(Region)),
+        "distance" => Ok(Arc::new(DistanceMobility)),
+        _ => Err(EvalError::UnknownEvaluator(name.to_string())),
+    }
+}
+
+/// Parses an evaluator spec: a name from [`EVALUATOR_NAMES`], or a sum of
+/// optionally weighted names such as `mobility+0.5*region`.
+pub fn parse_evaluator(spec: &str) -> Result<Arc<dyn Evaluator>, EvalError> {
+    if spec.is_empty() {
+        return Err(EvalError::Empty);
+    }
+    if !spec.contains(['+', '*']) {
+        return evaluator_by_name(spec);
+    }
+    let mut terms = Vec::new();
+    for term in spec.split('+') {
+        let (weight, name) = match term.split_once('*') {
+            Some((weight, name)) => {
+                let weight = weight.parse::<f64>()
+                    .map_err(|_| EvalError::BadWeight(weight.to_string()))?;
+                (weight, name)
+            }
+            None => (1., term),
+        };
+        if name.is_empty() {
+            return Err(EvalError::Empty);
+        }
+        terms.push((weight, evaluator_by_name(name)?));
+    }
+    Ok(Arc::new(Weighted { terms }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qd::legalcomp::get_possible_legal_moves;
+    use crate::qd::utils::vgs;
+    use crate::rng::rng_from_seed;
+    use rand::Rng;
+
+    fn evaluators() -> Vec<Arc<dyn Evaluator>> {
+        EVALUATOR_NAMES
+            .iter()
+            .map(|name| parse_evaluator(name).unwrap())
+            .chain([parse_evaluator("mobility+0.5*region+-2*distance").unwrap()])
+            .collect()
+    }
+
+    #[test]
+    fn test_mobility() {
+        assert_eq!(Mobility.evaluate(&GameState::def()), 0.);
+        let state = vgs("
+            ########
+            ########
+            ########
+            ###B####
+            ########
+            ########
+            ##.#####
+            ...W####
+        ", true);
+        assert_eq!(Mobility.evaluate(&state), 4.);
+    }
+
+    #[test]
+    fn test_distance_layers() {
+        let state = vgs("
+            ########
+            ########
+            ########
+            ###B####
+            ########
+            ########
+            .#.#####
+            ...W####
+        ", true);
+        let layers = distance_layers(state.wqueen(), empty_squares(&state));
+        assert_eq!(layers, vec![1 << 0 | 1 << 1 | 1 << 2 | 1 << 10, 1 << 8]);
+        assert_eq!(DistanceMobility.evaluate(&state), 4.5);
+        assert_eq!(Region.evaluate(&state), 5.);
+    }
+
+    #[test]
+    fn test_region_split() {
+        // White owns the bottom half, black the top, and the middle rank
+        // is a tie.
+        let state = vgs("
+            ...B....
+            ........
+            ........
+            ........
+            ........
+            ........
+            ........
+            ...W....
+        ", true);
+        assert_eq!(Region.evaluate(&state), 0.);
+        let state = vgs("
+            ...B....
+            ########
+            ........
+            ........
+            ........
+            ........
+            ........
+            ...W....
+        ", true);
+        assert_eq!(Region.evaluate(&state), 47. - 7.);
+    }
+
+    #[test]
+    fn test_color_swap_negates() {
+        let mut rng = rng_from_seed(Some(71));
+        for _ in 0..200 {
+            let mut state = GameState::def_rand_with(&mut rng);
+            for _ in 0..rng.gen_range(0..10) {
+                let moves = get_possible_legal_moves(&state);
+                if state.result().is_some() || moves == 0 {
+                    break;
+                }
+                let to = moves.trailing_zeros() as u8;
+                state.make_move(to);
+            }
+            for evaluator in evaluators() {
+                let score = evaluator.evaluate(&state);
+                assert!((evaluator.evaluate(&state.color_swapped()) + score).abs() < 1e-9, "{}", state);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_evaluator() {
+        let state = GameState::from_notation("3B4/8/8/8/8/8/8/1x2W3 w").unwrap();
+        let combined = parse_evaluator("2*mobility+region").unwrap();
+        assert_eq!(combined.evaluate(&state), 2. * Mobility.evaluate(&state) + Region.evaluate(&state));
+        assert_eq!(parse_evaluator("").err(), Some(EvalError::Empty));
+        assert_eq!(parse_evaluator("mobility+").err(), Some(EvalError::Empty));
+        assert_eq!(parse_evaluator("space").err(), Some(EvalError::UnknownEvaluator("space".to_string())));
+        assert_eq!(parse_evaluator("x*region").err(), Some(EvalError::BadWeight("x".to_string())));
+    }
+}
@@ -0,0 +1,207 @@
+//! Move ordering for the alpha-beta search.
+//!
+//! Alpha-beta prunes the most when the best move comes first. Moves are
+//! tried in this order: taking the queen, the transposition table move,
+//! the killer moves of the ply, then by history score, then by how few
+//! replies they leave the opponent.
+
+use std::cmp::Reverse;
+use crate::qd::legalcomp::get_possible_legal_moves;
+use crate::qd::state::GameState;
+
+/// Killer moves kept per ply.
+const KILLERS: usize = 2;
+/// Plies with killer moves. No game lasts longer.
+const MAX_PLY: usize = 64;
+
+/// Which ordering heuristics a search uses.
+#[derive(PartialEq)]
+#[derive(Debug, Eq)]
+#[derive(Copy, Clone)]
+pub struct MoveOrdering {
+    /// Queen captures first.
+    pub captures: bool,
+    /// The best move the transposition table remembers.
+    pub tt_move: bool,
+    /// Moves that caused a cutoff at the same ply elsewhere in the tree.
+    pub killers: bool,
+    /// Moves that caused cutoffs anywhere, weighted by the depth left.
+    pub history: bool,
+    /// Moves leaving the opponent fewer replies.
+    pub static_score: bool,
+}
+
+impl MoveOrdering {
+    /// Moves by square, or shuffled.
+    pub const NONE: Self = Self {
+        captures: false, tt_move: false, killers: false, history: false, static_score: false,
+    };
+    pub const ALL: Self = Self {
+        captures: true, tt_move: true, killers: true, history: true, static_score: true,
+    };
+
+    pub fn is_none(&self) -> bool {
+        *self == Self::NONE
+    }
+}
+
+impl Default for MoveOrdering {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+/// What the killer and history heuristics have learned so far. Can be
+/// kept from one search to the next.
+#[derive(Clone)]
+pub struct OrderingTables {
+    killers: Vec<[Option<u8>; KILLERS]>,
+    /// Indexed by whether white moved, then by target square.
+    history: [[u64; 64]; 2],
+}
+
+impl Default for OrderingTables {
+    fn default() -> Self {
+        Self { killers: vec![[None; KILLERS]; MAX_PLY], history: [[0; 64]; 2] }
+    }
+}
+
+impl OrderingTables {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn killers(&self, ply: usize) -> [Option<u8>; KILLERS] {
+        self.killers.get(ply).copied().unwrap_or([None; KILLERS])
+    }
+
+    pub fn history(&self, is_white_turn: bool, to: u8) -> u64 {
+        self.history[is_white_turn as usize][to as usize]
+    }
+
+    /// Notes that moving to `to` in `state`, `ply` plies below the root
+    /// with `draft` search left, caused a cutoff.
+    pub fn record_cutoff(&mut self, state: &GameState, to: u8, ply: usize, draft: u8) {
+        if let Some(killers) = self.killers.get_mut(ply)
+            && killers[0] != Some(to)
+        {
+            killers[1] = killers[0];
+            killers[0] = Some(to);
+        }
+        let draft = draft as u64;
+        let entry = &mut self.history[state.is_white_turn() as usize][to as usize];
+        *entry = entry.saturating_add(draft * draft);
+    }
+
+    /// Sorts the `children` of `state` best first, keeping the current
+    /// order among moves the heuristics cannot tell apart.
+    pub fn order(
+        &self,
+        ordering: MoveOrdering,
+        state: &GameState,
+        children: &mut [(GameState, u8)],
+        tt_move: Option<u8>,
+        ply: usize,
+    ) {
+        if ordering.is_none() {
+            return;
+        }
+        let target = if state.is_white_turn() { state.bqueen() } else { state.wqueen() };
+        let killers = self.killers(ply);
+        children.sort_by_cached_key(|(child, to)| {
+            let to = *to;
+            let class = if ordering.captures && to == target {
+                3
+            } else if ordering.tt_move && Some(to) == tt_move {
+                2
+            } else if ordering.killers && killers.contains(&Some(to)) {
+                1
+            } else {
+                0
+            };
+            let history = if ordering.history { self.history(state.is_white_turn(), to) } else { 0 };
+            let replies = if ordering.static_score { get_possible_legal_moves(child).count_ones() } else { 0 };
+            (Reverse(class), Reverse(history), replies)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qd::utils::vgs;
+
+    fn children(state: &GameState) -> Vec<(GameState, u8)> {
+        crate::bot::search::get_children(state)
+    }
+
+    fn order(tables: &OrderingTables, ordering: MoveOrdering, state: &GameState, tt_move: Option<u8>) -> Vec<u8> {
+        let mut kids = children(state);
+        tables.order(ordering, state, &mut kids, tt_move, 3);
+        kids.into_iter().map(|(_, to)| to).collect()
+    }
+
+    #[test]
+    fn test_order() {
+        let state = vgs("
+            ########
+            ########
+            ####..##
+            ###B..##
+            ########
+            ########
+            ###.####
+            ##.W.###
+        ", true);
+        let tables = OrderingTables::new();
+        // d1 can go to c1, e1 and d2.
+        assert_eq!(order(&tables, MoveOrdering::NONE, &state, Some(4)), vec![2, 4, 11]);
+        assert_eq!(order(&tables, MoveOrdering::ALL, &state, Some(4)), vec![4, 2, 11]);
+
+        let mut tables = OrderingTables::new();
+        tables.record_cutoff(&state, 11, 3, 2);
+        assert_eq!(tables.killers(3), [Some(11), None]);
+        assert_eq!(tables.history(true, 11), 4);
+        assert_eq!(order(&tables, MoveOrdering::ALL, &state, None), vec![11, 2, 4]);
+        assert_eq!(order(&tables, MoveOrdering::ALL, &state, Some(4)), vec![4, 11, 2]);
+        let history_only = MoveOrdering { history: true, ..MoveOrdering::NONE };
+        assert_eq!(order(&tables, history_only, &state, None), vec![11, 2, 4]);
+    }
+
+    #[test]
+    fn test_captures_first_and_fewest_replies() {
+        let state = vgs("
+            ........
+            ........
+            ........
+            ........
+            ........
+            ........
+            ........
+            B..W....
+        ", true);
+        let static_only = MoveOrdering { static_score: true, ..MoveOrdering::NONE };
+        let moves = order(&OrderingTables::new(), MoveOrdering::ALL, &state, None);
+        assert_eq!(moves[0], 0);
+        let replies = |to: u8| {
+            let mut child = state;
+            child.make_move(to);
+            get_possible_legal_moves(&child).count_ones()
+        };
+        let moves = order(&OrderingTables::new(), static_only, &state, None);
+        assert!(moves.windows(2).all(|pair| replies(pair[0]) <= replies(pair[1])));
+    }
+
+    #[test]
+    fn test_killers_shift() {
+        let state = GameState::def();
+        let mut tables = OrderingTables::new();
+        tables.record_cutoff(&state, 12, 5, 1);
+        tables.record_cutoff(&state, 12, 5, 1);
+        tables.record_cutoff(&state, 20, 5, 1);
+        assert_eq!(tables.killers(5), [Some(20), Some(12)]);
+        assert_eq!(tables.killers(MAX_PLY + 1), [None, None]);
+        tables.record_cutoff(&state, 20, MAX_PLY + 1, 1);
+        assert_eq!(tables.history(true, 12), 2);
+    }
+}
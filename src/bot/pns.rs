@@ -0,0 +1,393 @@
+//! Depth-first proof-number search (df-pn).
+//!
+//! Each node keeps a proof number, the least number of leaves that still
+//! have to be solved to show that the side to move wins, and a disproof
+//! number, the same for showing that it loses. Seen from the side to move,
+//! a node's proof number is the smallest disproof number of its children
+//! and its disproof number is the sum of their proof numbers. The search
+//! always expands the most proving child and stays in a subtree until its
+//! numbers cross the thresholds passed down to it.
+//!
+//! Every move fills an empty square, so the game tree has no cycles and the
+//! plain algorithm is exact.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::bot::separated::{solve_separated, LEAF_NODE_LIMIT};
+use crate::qd::legalcomp::get_possible_legal_moves;
+use crate::qd::state::GameState;
+use crate::tablebase::probe::Tablebase;
+
+const INF: u32 = u32::MAX;
+/// Rough size of one transposition table entry, hash map overhead included.
+const ENTRY_BYTES: usize = 40;
+
+#[derive(Copy, Clone)]
+struct Entry {
+    pn: u32,
+    dn: u32,
+    /// Nodes spent below this entry, used to decide what to drop when the
+    /// table is full.
+    work: u32,
+}
+
+const WIN: Entry = Entry { pn: 0, dn: INF, work: 0 };
+const LOSS: Entry = Entry { pn: INF, dn: 0, work: 0 };
+const UNKNOWN: Entry = Entry { pn: 1, dn: 1, work: 0 };
+
+#[derive(PartialEq)]
+#[derive(Debug, Eq)]
+#[derive(Clone)]
+pub struct SolveResult {
+    /// The proven winner, or `None` if a limit was hit first.
+    pub winner: Option<bool>,
+    /// The game from the position on, with the winner hurrying towards
+    /// the proven result. Empty if nothing was proven.
+    pub line: Vec<u8>,
+    /// Whether `line` reaches the end of the game. Building it gets a node
+    /// budget of its own, as large as the proof's, and stops short if that
+    /// runs out.
+    pub complete: bool,
+    pub nodes: u64,
+}
+
+pub struct Solver {
+    table: HashMap<u64, Entry>,
+    capacity: usize,
+    node_limit: u64,
+    nodes: u64,
+    tablebase: Option<Arc<Tablebase>>,
+}
+
+fn children(state: &GameState) -> Vec<(u8, GameState)> {
+    let mut res = Vec::new();
+    let mut rest = get_possible_legal_moves(state);
+    while rest != 0 {
+        let to = rest.trailing_zeros() as u8;
+        rest &= rest - 1;
+        let mut child = *state;
+        child.make_move(to);
+        res.push((to, child));
+    }
+    res
+}
+
+impl Solver {
+    /// A solver whose transposition table stays within about `memory_mb`
+    /// megabytes and that gives up after `node_limit` expansions.
+    pub fn new(memory_mb: usize, node_limit: u64) -> Self {
+        Self {
+            table: HashMap::new(),
+            capacity: (memory_mb * 1024 * 1024 / ENTRY_BYTES).max(1024),
+            node_limit,
+            nodes: 0,
+            tablebase: None,
+        }
+    }
+
+    /// Lets the solver treat positions the tablebase covers as solved.
+    pub fn with_tablebase(mut self, tablebase: Option<Arc<Tablebase>>) -> Self {
+        self.tablebase = tablebase;
+        self
+    }
+
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    pub fn table_len(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Whether the side to move wins, if that is known without search.
+    fn known_result(&self, state: &GameState) -> Option<bool> {
+        if state.result().is_some() {
+            return Some(false);
+        }
+        let other = if state.is_white_turn() { state.bqueen() } else { state.wqueen() };
+        if get_possible_legal_moves(state) & 1u64 << other != 0 {
+            return Some(true);
+        }
+        if let Some(entry) = self.tablebase.as_ref().and_then(|tb| tb.probe(state)) {
+            return Some(entry.win);
+        }
+        solve_separated(state, LEAF_NODE_LIMIT)
+            .and_then(|solved| solved.winner)
+            .map(|winner| winner == state.is_white_turn())
+    }
+
+    fn lookup(&mut self, state: &GameState) -> Entry {
+        if let Some(entry) = self.table.get(&state.zobrist()) {
+            return *entry;
+        }
+        let entry = match self.known_result(state) {
+            Some(true) => WIN,
+            Some(false) => LOSS,
+            None => return UNKNOWN,
+        };
+        self.store(state, entry);
+        entry
+    }
+
+    fn store(&mut self, state: &GameState, entry: Entry) {
+        if self.table.len() >= self.capacity {
+            self.collect_garbage();
+        }
+        self.table.insert(state.zobrist(), entry);
+    }
+
+    /// Drops the cheaper half of the open entries. Solved entries never
+    /// change, so they go only if the open ones are not enough.
+    fn collect_garbage(&mut self) {
+        let mut works: Vec<u32> = self.table.values()
+            .filter(|entry| entry.pn != 0 && entry.dn != 0)
+            .map(|entry| entry.work)
+            .collect();
+        if !works.is_empty() {
+            let middle = works.len() / 2;
+            let cut = *works.select_nth_unstable(middle).1;
+            self.table.retain(|_, entry| entry.pn == 0 || entry.dn == 0 || entry.work > cut);
+        }
+        if self.table.len() >= self.capacity * 3 / 4 {
+            self.table.retain(|_, entry| entry.pn == 0 || entry.dn == 0);
+        }
+        if self.table.len() >= self.capacity * 3 / 4 {
+            self.table.clear();
+        }
+    }
+
+    /// Expands `state` until its numbers reach the thresholds or the node
+    /// budget runs out.
+    fn mid(&mut self, state: &GameState, pn_limit: u32, dn_limit: u32) -> Entry {
+        let entry = self.lookup(state);
+        if entry.pn >= pn_limit || entry.dn >= dn_limit {
+            return entry;
+        }
+        let start_nodes = self.nodes;
+        let kids = children(state);
+        let mut entry = entry;
+        loop {
+            if self.nodes >= self.node_limit {
+                break;
+            }
+            self.nodes += 1;
+
+            let mut pn = INF;
+            let mut dn: u32 = 0;
+            let mut best = 0;
+            let mut second_dn = INF;
+            let mut best_pn = 0;
+            for (i, (_, child)) in kids.iter().enumerate() {
+                let child_entry = self.lookup(child);
+                dn = dn.saturating_add(child_entry.pn);
+                if child_entry.dn < pn {
+                    second_dn = pn;
+                    pn = child_entry.dn;
+                    best = i;
+                    best_pn = child_entry.pn;
+                } else if child_entry.dn < second_dn {
+                    second_dn = child_entry.dn;
+                }
+            }
+            entry = Entry { pn, dn, work: (self.nodes - start_nodes).min(u32::MAX as u64) as u32 };
+            if pn >= pn_limit || dn >= dn_limit {
+                break;
+            }
+            let child_pn_limit = (dn_limit as u64 - dn as u64 + best_pn as u64).min(INF as u64) as u32;
+            let child_dn_limit = pn_limit.min(second_dn.saturating_add(1));
+            let child = kids[best].1;
+            self.mid(&child, child_pn_limit, child_dn_limit);
+        }
+        self.store(state, entry);
+        entry
+    }
+
+    /// Whether the side to move in `state` wins, or `None` if the limits
+    /// were hit first.
+    pub fn prove(&mut self, state: &GameState) -> Option<bool> {
+        let entry = self.mid(state, INF, INF);
+        if entry.pn == 0 {
+            Some(true)
+        } else if entry.dn == 0 {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the side to move in `state` wins, if the table already
+    /// knows.
+    fn proven(&self, state: &GameState) -> Option<bool> {
+        match self.table.get(&state.zobrist()) {
+            Some(entry) if entry.pn == 0 => Some(true),
+            Some(entry) if entry.dn == 0 => Some(false),
+            _ => None,
+        }
+    }
+
+    /// The move the side to move should play in a proven position. The
+    /// winner takes the winning move that was cheapest to prove, which is
+    /// usually the quickest, and the loser the one that was hardest.
+    /// Children the table has solved are tried first; the rest are only
+    /// searched if none of those will do.
+    fn line_move(&mut self, state: &GameState, wins: bool) -> Option<(u8, GameState)> {
+        let kids = children(state);
+        let known: Vec<_> = kids.iter()
+            .filter_map(|&(to, child)| Some((to, child, self.proven(&child)?)))
+            .collect();
+        if let Some(best) = self.pick_line_move(&known, wins) {
+            return Some(best);
+        }
+        let searched: Vec<_> = kids.iter()
+            .filter_map(|&(to, child)| Some((to, child, self.prove(&child)?)))
+            .collect();
+        self.pick_line_move(&searched, wins)
+    }
+
+    fn pick_line_move(&self, candidates: &[(u8, GameState, bool)], wins: bool) -> Option<(u8, GameState)> {
+        let mut best: Option<(u8, GameState, u32)> = None;
+        for &(to, child, child_wins) in candidates {
+            if child_wins == wins {
+                continue;
+            }
+            let work = self.table.get(&child.zobrist()).map_or(0, |entry| entry.work);
+            let better = match best {
+                None => true,
+                Some((_, _, best_work)) => if wins { work < best_work } else { work > best_work },
+            };
+            if better {
+                best = Some((to, child, work));
+            }
+        }
+        best.map(|(to, child, _)| (to, child))
+    }
+
+    /// The line from `state`, whose `winner` is proven, and whether it
+    /// reaches the end of the game before the node budget runs out.
+    fn line(&mut self, state: &GameState, winner: bool) -> (Vec<u8>, bool) {
+        let mut line = Vec::new();
+        let mut current = *state;
+        while current.result().is_none() {
+            let wins = current.is_white_turn() == winner;
+            match self.line_move(&current, wins) {
+                Some((to, child)) => {
+                    line.push(to);
+                    current = child;
+                }
+                None => break,
+            }
+        }
+        (line, current.result().is_some())
+    }
+
+    pub fn solve(&mut self, state: &GameState) -> SolveResult {
+        let Some(mover_wins) = self.prove(state) else {
+            return SolveResult { winner: None, line: Vec::new(), complete: false, nodes: self.nodes };
+        };
+        let winner = mover_wins == state.is_white_turn();
+        let proof_limit = self.node_limit;
+        self.node_limit = self.nodes.saturating_add(proof_limit);
+        let (line, complete) = self.line(state, winner);
+        self.node_limit = proof_limit;
+        SolveResult { winner: Some(winner), line, complete, nodes: self.nodes }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qd::startpos::forced_result;
+    use crate::rng::rng_from_seed;
+    use rand::Rng;
+
+    /// A position with `empty` empty squares that needs real search.
+    fn random_state(rng: &mut impl Rng, empty: u32) -> GameState {
+        loop {
+            let wqueen = rng.gen_range(0..64u8);
+            let bqueen = (wqueen + rng.gen_range(1..64u8)) % 64;
+            let mut blocks = !(1u64 << wqueen) & !(1u64 << bqueen);
+            while (!blocks).count_ones() < empty + 2 {
+                blocks &= !(1u64 << rng.gen_range(0..64));
+            }
+            let state = GameState::new(Some(wqueen), Some(bqueen), Some(blocks), Some(rng.gen_bool(0.5)));
+            if Solver::new(1, 0).known_result(&state).is_none() {
+                return state;
+            }
+        }
+    }
+
+    #[test]
+    fn test_matches_full_search() {
+        let mut rng = rng_from_seed(Some(51));
+        for _ in 0..200 {
+            let state = random_state(&mut rng, 10);
+            let mut solver = Solver::new(16, u64::MAX);
+            let result = solver.solve(&state);
+            assert_eq!(result.winner, forced_result(&state, 64), "{}", state);
+        }
+    }
+
+    #[test]
+    fn test_line_is_legal_and_ends_with_winner() {
+        let mut rng = rng_from_seed(Some(52));
+        for _ in 0..50 {
+            let state = random_state(&mut rng, 10);
+            let result = Solver::new(16, u64::MAX).solve(&state);
+            let mut current = state;
+            for &to in &result.line {
+                current.try_make_move(to).unwrap();
+            }
+            assert_eq!(current.result(), result.winner, "{}", state);
+            assert!(result.complete);
+        }
+    }
+
+    #[test]
+    fn test_node_limit() {
+        let result = Solver::new(16, 100).solve(&GameState::def());
+        assert_eq!(result.winner, None);
+        assert!(result.line.is_empty());
+        assert!(!result.complete);
+        assert!(result.nodes <= 100);
+    }
+
+    #[test]
+    fn test_small_table() {
+        // A table far too small to hold the tree still gives the right answer.
+        let mut rng = rng_from_seed(Some(53));
+        for _ in 0..20 {
+            let state = random_state(&mut rng, 14);
+            let mut solver = Solver::new(0, u64::MAX);
+            solver.capacity = 64;
+            assert_eq!(solver.solve(&state).winner, Solver::new(16, u64::MAX).solve(&state).winner);
+        }
+    }
+
+    #[test]
+    fn test_line_budget_runs_out() {
+        let mut rng = rng_from_seed(Some(54));
+        // A position whose line is long enough to need search after the
+        // first move.
+        let (state, full) = loop {
+            let state = random_state(&mut rng, 14);
+            let full = Solver::new(16, u64::MAX).solve(&state);
+            if full.line.len() >= 8 {
+                break (state, full);
+            }
+        };
+        assert!(full.complete);
+        // A solver with no nodes left that only knows the first move.
+        let mut solver = Solver::new(16, 0);
+        let mut first = state;
+        first.make_move(full.line[0]);
+        solver.store(&first, LOSS);
+        let (line, complete) = solver.line(&state, full.winner.unwrap());
+        assert!(!complete);
+        assert_eq!(line[0], full.line[0]);
+        let mut current = state;
+        for &to in &line {
+            current.try_make_move(to).unwrap();
+        }
+        assert_eq!(current.result(), None);
+    }
+}
@@ -0,0 +1,470 @@
+//! Every bot by name, with the parameters it takes.
+//!
+//! A bot string names a bot and sets some of its parameters, e.g.
+//! `adapt(budget=4096,eval=region)`; the rest keep their defaults. The
+//! older short forms such as `basic3-region@16:4` are still read: the
+//! suffixes `-<eval>`, `@<tt>` and `:<threads>` set those parameters, and
+//! what follows the name is read by each bot in its own way.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::bot::base::Bot;
+use crate::bot::collections::{adapt, basic, mcts, random, timed, weak};
+use crate::bot::eval::{parse_evaluator, Evaluator};
+use crate::bot::search::MAX_DEPTH;
+
+/// What values a parameter takes.
+#[derive(PartialEq)]
+#[derive(Debug, Eq)]
+#[derive(Copy, Clone)]
+pub enum ParamKind {
+    Int { min: u64, max: u64 },
+    /// A positive duration such as `500ms` or `2s`.
+    Duration,
+    /// An evaluator spec, see [`parse_evaluator`].
+    Evaluator,
+    Choice(&'static [&'static str]),
+    /// An iteration count or a duration.
+    Budget,
+}
+
+impl fmt::Display for ParamKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamKind::Int { min, max: u64::MAX } => write!(f, "an integer of at least {}", min),
+            ParamKind::Int { min, max } => write!(f, "an integer from {} to {}", min, max),
+            ParamKind::Duration => write!(f, "a duration such as 500ms or 2s"),
+            ParamKind::Evaluator => write!(f, "an evaluator such as region or mobility+0.5*region"),
+            ParamKind::Choice(options) => write!(f, "one of {}", options.join(", ")),
+            ParamKind::Budget => write!(f, "an iteration count or a duration such as 500ms"),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum ParamValue {
+    Int(u64),
+    Duration(Duration),
+    Evaluator(Arc<dyn Evaluator>),
+    Choice(&'static str),
+}
+
+impl ParamKind {
+    fn parse(&self, value: &str) -> Option<ParamValue> {
+        match *self {
+            ParamKind::Int { min, max } => {
+                let n = value.parse::<u64>().ok()?;
+                (min..=max).contains(&n).then_some(ParamValue::Int(n))
+            }
+            ParamKind::Duration => parse_duration(value).map(ParamValue::Duration),
+            ParamKind::Evaluator => parse_evaluator(value).ok().map(ParamValue::Evaluator),
+            ParamKind::Choice(options) => options.iter().find(|&&o| o == value).map(|&o| ParamValue::Choice(o)),
+            ParamKind::Budget => match value.parse::<u64>() {
+                Ok(0) => None,
+                Ok(n) => Some(ParamValue::Int(n)),
+                Err(_) => parse_duration(value).map(ParamValue::Duration),
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParamSpec {
+    pub name: &'static str,
+    pub kind: ParamKind,
+    pub default: &'static str,
+    pub help: &'static str,
+}
+
+/// A bot's parameters, every one set.
+pub struct Params {
+    values: Vec<(&'static str, ParamValue)>,
+}
+
+impl Params {
+    /// The value of parameter `name`, which the bot must have.
+    pub fn get(&self, name: &str) -> &ParamValue {
+        self.values
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, value)| value)
+            .unwrap_or_else(|| panic!("no parameter {}", name))
+    }
+
+    pub fn int(&self, name: &str) -> u64 {
+        match self.get(name) {
+            ParamValue::Int(n) => *n,
+            _ => panic!("{} is not an integer", name),
+        }
+    }
+
+    pub fn duration(&self, name: &str) -> Duration {
+        match self.get(name) {
+            ParamValue::Duration(time) => *time,
+            _ => panic!("{} is not a duration", name),
+        }
+    }
+
+    pub fn evaluator(&self, name: &str) -> Arc<dyn Evaluator> {
+        match self.get(name) {
+            ParamValue::Evaluator(evaluator) => evaluator.clone(),
+            _ => panic!("{} is not an evaluator", name),
+        }
+    }
+
+    pub fn choice(&self, name: &str) -> &'static str {
+        match self.get(name) {
+            ParamValue::Choice(choice) => choice,
+            _ => panic!("{} is not a choice", name),
+        }
+    }
+}
+
+/// Parameter settings read from the short form after the bot's name.
+type Shorthand = fn(&str) -> Option<Vec<(&'static str, String)>>;
+
+pub struct BotEntry {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub params: &'static [ParamSpec],
+    /// An example of the short form, if the bot has one.
+    pub shorthand_example: Option<&'static str>,
+    shorthand: Option<Shorthand>,
+    build: fn(&Params) -> Box<dyn Bot>,
+}
+
+impl BotEntry {
+    fn param(&self, name: &str) -> Result<&ParamSpec, BotSpecError> {
+        self.params.iter().find(|spec| spec.name == name).ok_or_else(|| BotSpecError::UnknownParam {
+            bot: self.name,
+            param: name.to_string(),
+        })
+    }
+
+    /// Builds the bot with `settings` and defaults for the rest.
+    fn build(&self, settings: &[(&str, String)]) -> Result<Box<dyn Bot>, BotSpecError> {
+        for (i, (name, _)) in settings.iter().enumerate() {
+            self.param(name)?;
+            if settings[..i].iter().any(|(earlier, _)| earlier == name) {
+                return Err(BotSpecError::DuplicateParam { bot: self.name, param: name.to_string() });
+            }
+        }
+        let mut values = Vec::new();
+        for spec in self.params {
+            let value = settings
+                .iter()
+                .find(|(name, _)| *name == spec.name)
+                .map_or(spec.default, |(_, value)| value.as_str());
+            let parsed = spec.kind.parse(value).ok_or_else(|| BotSpecError::BadValue {
+                bot: self.name,
+                param: spec.name,
+                value: value.to_string(),
+                expected: spec.kind,
+            })?;
+            values.push((spec.name, parsed));
+        }
+        Ok((self.build)(&Params { values }))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BotSpecError {
+    UnknownBot(String),
+    UnknownParam { bot: &'static str, param: String },
+    DuplicateParam { bot: &'static str, param: String },
+    BadValue { bot: &'static str, param: &'static str, value: String, expected: ParamKind },
+    /// A short form the bot cannot read.
+    BadShorthand { bot: &'static str, text: String },
+    Syntax(String),
+}
+
+impl fmt::Display for BotSpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BotSpecError::UnknownBot(name) => write!(
+                f, "unknown bot \"{}\", expected one of: {}", name, bot_names().join(", ")
+            ),
+            BotSpecError::UnknownParam { bot, param } => {
+                let names: Vec<&str> = find_bot(bot).unwrap().params.iter().map(|spec| spec.name).collect();
+                if names.is_empty() {
+                    write!(f, "{} takes no parameters, got \"{}\"", bot, param)
+                } else {
+                    write!(f, "{} has no parameter \"{}\", expected one of: {}", bot, param, names.join(", "))
+                }
+            }
+            BotSpecError::DuplicateParam { bot, param } => write!(f, "{} sets \"{}\" twice", bot, param),
+            BotSpecError::BadValue { bot, param, value, expected } => write!(
+                f, "invalid {} \"{}\" for {}, expected {}", param, value, bot, expected
+            ),
+            BotSpecError::BadShorthand { bot, text } => write!(
+                f, "cannot read \"{}\" after {}, write parameters as {}(name=value,...)", text, bot, bot
+            ),
+            BotSpecError::Syntax(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for BotSpecError {}
+
+/// Parses a positive duration such as `500ms` or `2s`.
+pub fn parse_duration(s: &str) -> Option<Duration> {
+    let time = if let Some(ms) = s.strip_suffix("ms") {
+        Duration::from_millis(ms.parse().ok()?)
+    } else if let Some(secs) = s.strip_suffix('s') {
+        Duration::try_from_secs_f64(secs.parse().ok()?).ok()?
+    } else {
+        return None;
+    };
+    if time.is_zero() { None } else { Some(time) }
+}
+
+const EVAL: ParamSpec = ParamSpec {
+    name: "eval", kind: ParamKind::Evaluator, default: "mobility", help: "scores the positions the search stops at",
+};
+const TT: ParamSpec = ParamSpec {
+    name: "tt", kind: ParamKind::Int { min: 0, max: 4096 }, default: "1",
+    help: "transposition table size in megabytes, 0 for none",
+};
+const THREADS: ParamSpec = ParamSpec {
+    name: "threads", kind: ParamKind::Int { min: 1, max: 256 }, default: "1",
+    help: "threads searching each move, sharing the table",
+};
+
+/// Reads the whole short form as parameter `name`.
+fn shorthand_for(name: &'static str, text: &str) -> Option<Vec<(&'static str, String)>> {
+    Some(vec![(name, text.to_string())])
+}
+
+fn adapt_shorthand(text: &str) -> Option<Vec<(&'static str, String)>> {
+    // adaptN searches 2^(N+4) nodes.
+    let n = text.parse::<u32>().ok().filter(|&n| n < 60)?;
+    let budget = if n == 0 { 0 } else { 1u64 << (n + 4) };
+    Some(vec![("budget", budget.to_string())])
+}
+
+fn mcts_shorthand(text: &str) -> Option<Vec<(&'static str, String)>> {
+    match text.strip_prefix('r') {
+        Some(budget) => Some(vec![("playout", "random".to_string()), ("budget", budget.to_string())]),
+        None => shorthand_for("budget", text),
+    }
+}
+
+pub static BOTS: &[BotEntry] = &[
+    BotEntry {
+        name: "random",
+        help: "Plays uniformly random moves.",
+        params: &[],
+        shorthand_example: None,
+        shorthand: None,
+        build: |_| Box::new(random::RandomBot::new()),
+    },
+    BotEntry {
+        name: "weak",
+        help: "Picks between the best moves at depth 1 and 2, leaning towards depth 2 as the level goes up.",
+        params: &[
+            ParamSpec { name: "level", kind: ParamKind::Int { min: 0, max: 10 }, default: "5", help: "strength" },
+            EVAL,
+        ],
+        shorthand_example: Some("weak5-region"),
+        shorthand: Some(|text| shorthand_for("level", text)),
+        build: |p| Box::new(weak::WeakBot::new(p.int("level") as f64 / 10.).evaluator(p.evaluator("eval"))),
+    },
+    BotEntry {
+        name: "basic",
+        help: "Full-width alpha-beta to a fixed depth.",
+        params: &[
+            ParamSpec {
+                name: "depth", kind: ParamKind::Int { min: 1, max: MAX_DEPTH as u64 }, default: "3",
+                help: "plies to search",
+            },
+            EVAL, TT, THREADS,
+        ],
+        shorthand_example: Some("basic3-region@16:4"),
+        shorthand: Some(|text| shorthand_for("depth", text)),
+        build: |p| Box::new(
+            basic::BasicBot::new(p.int("depth") as u32)
+                .evaluator(p.evaluator("eval"))
+                .tt_size(p.int("tt") as usize)
+                .threads(p.int("threads") as usize)
+        ),
+    },
+    BotEntry {
+        name: "adapt",
+        help: "Alpha-beta over a node budget, so forcing lines get searched deeper.",
+        params: &[
+            ParamSpec {
+                name: "budget", kind: ParamKind::Int { min: 1, max: u64::MAX }, default: "512",
+                help: "nodes to search",
+            },
+            EVAL, TT,
+        ],
+        shorthand_example: Some("adapt5 for 2^(5+4) nodes"),
+        shorthand: Some(adapt_shorthand),
+        build: |p| Box::new(
+            adapt::AdaptiveBot::new(p.int("budget"))
+                .evaluator(p.evaluator("eval"))
+                .tt_size(p.int("tt") as usize)
+        ),
+    },
+    BotEntry {
+        name: "time",
+        help: "Iterative-deepening alpha-beta for a fixed time per move.",
+        params: &[
+            ParamSpec { name: "time", kind: ParamKind::Duration, default: "500ms", help: "time per move" },
+            EVAL, TT, THREADS,
+        ],
+        shorthand_example: Some("time500ms@64:4"),
+        shorthand: Some(|text| shorthand_for("time", text)),
+        build: |p| Box::new(
+            timed::TimedBot::new(p.duration("time"))
+                .evaluator(p.evaluator("eval"))
+                .tt_size(p.int("tt") as usize)
+                .threads(p.int("threads") as usize)
+        ),
+    },
+    BotEntry {
+        name: "mcts",
+        help: "Monte Carlo tree search with UCT.",
+        params: &[
+            ParamSpec { name: "budget", kind: ParamKind::Budget, default: "1000", help: "iterations or time per move" },
+            ParamSpec {
+                name: "playout", kind: ParamKind::Choice(&["light", "random"]), default: "light",
+                help: "light takes the queen and avoids its reach, random plays any move",
+            },
+        ],
+        shorthand_example: Some("mcts1000, mcts50ms, or mctsr1000 for random playouts"),
+        shorthand: Some(mcts_shorthand),
+        build: |p| {
+            let budget = match p.get("budget") {
+                ParamValue::Duration(time) => mcts::MctsBudget::Time(*time),
+                _ => mcts::MctsBudget::Iterations(p.int("budget")),
+            };
+            let playout = match p.choice("playout") {
+                "random" => mcts::Playout::Random,
+                _ => mcts::Playout::Light,
+            };
+            Box::new(mcts::MctsBot::new(budget).playout(playout))
+        },
+    },
+];
+
+pub fn bot_names() -> Vec<&'static str> {
+    BOTS.iter().map(|entry| entry.name).collect()
+}
+
+pub fn find_bot(name: &str) -> Option<&'static BotEntry> {
+    BOTS.iter().find(|entry| entry.name == name)
+}
+
+/// Parses `name(param=value,...)`.
+fn parse_call(spec: &str) -> Result<Box<dyn Bot>, BotSpecError> {
+    let (name, rest) = spec.split_once('(').unwrap();
+    let entry = find_bot(name).ok_or_else(|| BotSpecError::UnknownBot(name.to_string()))?;
+    let inner = rest
+        .strip_suffix(')')
+        .ok_or_else(|| BotSpecError::Syntax(format!("missing \")\" at the end of \"{}\"", spec)))?;
+    let mut settings = Vec::new();
+    for setting in inner.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let (param, value) = setting
+            .split_once('=')
+            .ok_or_else(|| BotSpecError::Syntax(format!("expected name=value, got \"{}\"", setting)))?;
+        settings.push((param.trim(), value.trim().to_string()));
+    }
+    entry.build(&settings)
+}
+
+/// Parses the short form, e.g. `basic3-region@16:4`.
+fn parse_short(spec: &str) -> Result<Box<dyn Bot>, BotSpecError> {
+    let mut settings = Vec::new();
+    let mut spec = spec;
+    if let Some((rest, threads)) = spec.rsplit_once(':') {
+        settings.push(("threads", threads.to_string()));
+        spec = rest;
+    }
+    if let Some((rest, tt)) = spec.rsplit_once('@') {
+        settings.push(("tt", tt.to_string()));
+        spec = rest;
+    }
+    if let Some((rest, eval)) = spec.split_once('-') {
+        settings.push(("eval", eval.to_string()));
+        spec = rest;
+    }
+    let split = spec.find(|c: char| !c.is_ascii_lowercase()).unwrap_or(spec.len());
+    // Names are matched longest first, so that "mctsr" is mcts with
+    // random playouts.
+    let entry = BOTS
+        .iter()
+        .filter(|entry| spec.starts_with(entry.name))
+        .max_by_key(|entry| entry.name.len())
+        .filter(|entry| entry.name.len() == split || entry.shorthand.is_some())
+        .ok_or_else(|| BotSpecError::UnknownBot(spec[..split].to_string()))?;
+    let text = &spec[entry.name.len()..];
+    if !text.is_empty() {
+        let shorthand = entry.shorthand
+            .and_then(|shorthand| shorthand(text))
+            .ok_or_else(|| BotSpecError::BadShorthand { bot: entry.name, text: text.to_string() })?;
+        settings.extend(shorthand);
+    }
+    entry.build(&settings)
+}
+
+/// Builds the bot a bot string describes: `name`, `name(param=value,...)`
+/// or a short form such as `basic3-region@16:4`.
+pub fn parse_bot(spec: &str) -> Result<Box<dyn Bot>, BotSpecError> {
+    let spec = spec.trim();
+    if spec.is_empty() {
+        return Err(BotSpecError::Syntax("empty bot string".to_string()));
+    }
+    if spec.contains('(') {
+        parse_call(spec)
+    } else {
+        parse_short(spec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(spec: &str) -> String {
+        parse_bot(spec).err().map(|err| err.to_string()).unwrap_or_default()
+    }
+
+    #[test]
+    fn test_parse_bot() {
+        for spec in [
+            "random", "random()", "basic", "basic(depth=5)", "adapt(budget=4096,eval=region)",
+            "basic( depth = 4 , eval=mobility+0.5*region, tt=0 )", "time(time=1.5s,threads=2)",
+            "mcts(budget=50ms,playout=random)", "weak(level=0)",
+        ] {
+            assert!(parse_bot(spec).is_ok(), "{}", spec);
+        }
+        assert_eq!(
+            error("adapt(budget=0)"),
+            "invalid budget \"0\" for adapt, expected an integer of at least 1",
+        );
+        assert_eq!(error("weak11"), "invalid level \"11\" for weak, expected an integer from 0 to 10");
+        assert_eq!(error("basic(depht=3)"), "basic has no parameter \"depht\", expected one of: depth, eval, tt, threads");
+        assert_eq!(error("random@4"), "random takes no parameters, got \"tt\"");
+        assert_eq!(error("adapt5:2"), "adapt has no parameter \"threads\", expected one of: budget, eval, tt");
+        assert_eq!(error("basic(tt=8192)"), "invalid tt \"8192\" for basic, expected an integer from 0 to 4096");
+        assert_eq!(error("basic(depth=3,depth=4)"), "basic sets \"depth\" twice");
+        assert_eq!(error("basic(depth=3"), "missing \")\" at the end of \"basic(depth=3\"");
+        assert_eq!(error("basic(3)"), "expected name=value, got \"3\"");
+        assert_eq!(error("mcts(playout=heavy)"), "invalid playout \"heavy\" for mcts, expected one of light, random");
+        assert!(error("minimax3").starts_with("unknown bot \"minimax\""));
+        assert!(error("randomx").starts_with("unknown bot \"randomx\""));
+        assert_eq!(error("timefast"), "invalid time \"fast\" for time, expected a duration such as 500ms or 2s");
+        assert!(error("adapt5x").starts_with("cannot read \"5x\" after adapt"));
+    }
+
+    #[test]
+    fn test_defaults_are_valid() {
+        for entry in BOTS {
+            for spec in entry.params {
+                assert!(spec.kind.parse(spec.default).is_some(), "{}.{}", entry.name, spec.name);
+            }
+        }
+    }
+}
@@ -0,0 +1,899 @@
+//! Alpha-beta search shared by the minimax bots.
+//!
+//! A [`Searcher`] combines an [`Evaluator`], which scores the positions the
+//! search stops at, with a [`SearchLimit`], which decides where it stops.
+//! Scores are always from white's point of view.
+
+use std::cmp::min;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use crate::bot::base::SearchInfo;
+use crate::bot::eval::Evaluator;
+use crate::bot::ordering::{MoveOrdering, OrderingTables};
+use crate::bot::separated::{solve_separated, solve_separated_by, SeparatedResult, LEAF_NODE_LIMIT, ROOT_NODE_LIMIT};
+use crate::bot::tt::{Bound, TranspositionTable, TtEntry};
+use crate::qd::legalcomp::{get_possible_attack_mask, get_possible_legal_moves};
+use crate::qd::regions::queens_share_region;
+use crate::qd::state::GameState;
+use crate::rng::{next_seed, seed_thread, with_rng};
+
+/// Score of a won position.
+pub const INFINITY: f64 = 1e6;
+
+/// Decides how much of the tree a search covers. Each node gets a budget,
+/// hands parts of it to its children one at a time, and becomes a leaf
+/// once its budget is used up.
+pub trait SearchLimit: Send + Sync {
+    type Budget: Copy;
+
+    fn root_budget(&self) -> Self::Budget;
+
+    /// Whether a node with `budget` must be scored without search.
+    fn is_leaf(&self, budget: Self::Budget) -> bool;
+
+    /// Budget for the next child of a node with `remaining` left and
+    /// `moves_left` children still to search, this one included.
+    fn child_budget(&self, remaining: Self::Budget, moves_left: usize) -> Self::Budget;
+
+    /// What a node has left after giving `given` to a child that handed
+    /// back `left_over`.
+    fn charge(&self, remaining: Self::Budget, given: Self::Budget, left_over: Self::Budget) -> Self::Budget;
+
+    /// What a leaf with `budget` hands back.
+    fn leaf_left_over(&self, budget: Self::Budget) -> Self::Budget;
+
+    /// A rough measure of how much search `budget` buys, saturating at
+    /// 255. A transposition table result is reused only when it had at
+    /// least as much.
+    fn draft(&self, budget: Self::Budget) -> u8;
+
+    /// Whether the search must be abandoned. Checked about every
+    /// [`STOP_CHECK_INTERVAL`] nodes.
+    fn should_stop(&self) -> bool {
+        false
+    }
+
+    /// Plies every line is searched to, if the limit works by depth.
+    fn depth(&self) -> Option<u32> {
+        None
+    }
+}
+
+/// How many nodes a search visits between checks of
+/// [`SearchLimit::should_stop`].
+pub const STOP_CHECK_INTERVAL: u64 = 1024;
+
+/// Deeper than any game can last.
+pub const MAX_DEPTH: u32 = 64;
+
+/// Searches every line to the same number of plies.
+#[derive(Copy, Clone, Debug)]
+pub struct DepthLimit(pub u32);
+
+impl SearchLimit for DepthLimit {
+    type Budget = u32;
+
+    fn root_budget(&self) -> u32 {
+        self.0
+    }
+
+    fn is_leaf(&self, depth: u32) -> bool {
+        depth == 0
+    }
+
+    fn child_budget(&self, depth: u32, _moves_left: usize) -> u32 {
+        depth.saturating_sub(1)
+    }
+
+    fn charge(&self, depth: u32, _given: u32, _left_over: u32) -> u32 {
+        depth
+    }
+
+    fn leaf_left_over(&self, depth: u32) -> u32 {
+        depth
+    }
+
+    fn draft(&self, depth: u32) -> u8 {
+        depth.min(255) as u8
+    }
+
+    fn depth(&self) -> Option<u32> {
+        Some(self.0)
+    }
+}
+
+/// Shares a budget out evenly among the moves of each node, so lines with
+/// few replies are searched deeper. A leaf uses up whatever it was given,
+/// and a child that was cut off early hands the rest back to its siblings.
+#[derive(Copy, Clone, Debug)]
+pub struct NodeBudget(pub u64);
+
+impl SearchLimit for NodeBudget {
+    type Budget = u64;
+
+    fn root_budget(&self) -> u64 {
+        self.0
+    }
+
+    fn is_leaf(&self, budget: u64) -> bool {
+        budget == 0
+    }
+
+    fn child_budget(&self, remaining: u64, moves_left: usize) -> u64 {
+        min(remaining / moves_left as u64 * 5 / 4, remaining)
+    }
+
+    fn charge(&self, remaining: u64, given: u64, left_over: u64) -> u64 {
+        remaining - (given - left_over)
+    }
+
+    fn leaf_left_over(&self, _budget: u64) -> u64 {
+        0
+    }
+
+    fn draft(&self, budget: u64) -> u8 {
+        (64 - budget.leading_zeros()) as u8
+    }
+}
+
+/// Another limit with a deadline. A search still running when it passes
+/// is abandoned and its result marked incomplete.
+#[derive(Copy, Clone, Debug)]
+pub struct TimeLimit<L> {
+    pub inner: L,
+    pub deadline: Instant,
+}
+
+impl<L: SearchLimit> TimeLimit<L> {
+    /// `inner`, cut short `time` from now.
+    pub fn new(inner: L, time: Duration) -> Self {
+        Self { inner, deadline: Instant::now() + time }
+    }
+}
+
+impl<L: SearchLimit> SearchLimit for TimeLimit<L> {
+    type Budget = L::Budget;
+
+    fn root_budget(&self) -> L::Budget {
+        self.inner.root_budget()
+    }
+
+    fn is_leaf(&self, budget: L::Budget) -> bool {
+        self.inner.is_leaf(budget)
+    }
+
+    fn child_budget(&self, remaining: L::Budget, moves_left: usize) -> L::Budget {
+        self.inner.child_budget(remaining, moves_left)
+    }
+
+    fn charge(&self, remaining: L::Budget, given: L::Budget, left_over: L::Budget) -> L::Budget {
+        self.inner.charge(remaining, given, left_over)
+    }
+
+    fn leaf_left_over(&self, budget: L::Budget) -> L::Budget {
+        self.inner.leaf_left_over(budget)
+    }
+
+    fn draft(&self, budget: L::Budget) -> u8 {
+        self.inner.draft(budget)
+    }
+
+    fn depth(&self) -> Option<u32> {
+        self.inner.depth()
+    }
+
+    fn should_stop(&self) -> bool {
+        self.inner.should_stop() || Instant::now() >= self.deadline
+    }
+}
+
+/// Another limit that also stops once `stop` is set, e.g. by another
+/// thread.
+#[derive(Copy, Clone, Debug)]
+pub struct StopFlag<'a, L> {
+    pub inner: L,
+    pub stop: &'a AtomicBool,
+}
+
+impl<L: SearchLimit> SearchLimit for StopFlag<'_, L> {
+    type Budget = L::Budget;
+
+    fn root_budget(&self) -> L::Budget {
+        self.inner.root_budget()
+    }
+
+    fn is_leaf(&self, budget: L::Budget) -> bool {
+        self.inner.is_leaf(budget)
+    }
+
+    fn child_budget(&self, remaining: L::Budget, moves_left: usize) -> L::Budget {
+        self.inner.child_budget(remaining, moves_left)
+    }
+
+    fn charge(&self, remaining: L::Budget, given: L::Budget, left_over: L::Budget) -> L::Budget {
+        self.inner.charge(remaining, given, left_over)
+    }
+
+    fn leaf_left_over(&self, budget: L::Budget) -> L::Budget {
+        self.inner.leaf_left_over(budget)
+    }
+
+    fn draft(&self, budget: L::Budget) -> u8 {
+        self.inner.draft(budget)
+    }
+
+    fn depth(&self) -> Option<u32> {
+        self.inner.depth()
+    }
+
+    fn should_stop(&self) -> bool {
+        self.inner.should_stop() || self.stop.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Copy, Clone)]
+pub struct SearchResult {
+    pub score: f64,
+    pub best_move: Option<u8>,
+    /// Positions visited, leaves included.
+    pub nodes: u64,
+    /// False if the limit stopped the search before it finished, in which
+    /// case the score and move mean little.
+    pub complete: bool,
+    /// Transposition table lookups, and how many found the position.
+    pub tt_probes: u64,
+    pub tt_hits: u64,
+}
+
+impl SearchResult {
+    /// Share of transposition table lookups that found the position, if
+    /// there were any.
+    pub fn tt_hit_rate(&self) -> Option<f64> {
+        (self.tt_probes > 0).then(|| self.tt_hits as f64 / self.tt_probes as f64)
+    }
+
+    /// Whether the score is a forced win for either side.
+    pub fn is_proven(&self) -> bool {
+        self.score.abs() > INFINITY / 2.
+    }
+}
+
+/// Whether the side to move can take the other queen.
+pub fn queens_in_reach(state: &GameState) -> bool {
+    (get_possible_attack_mask(state.wqueen()) | get_possible_attack_mask(state.bqueen()))
+        & (1u64 << state.wqueen() | 1u64 << state.bqueen()) != 0
+        && get_possible_legal_moves(state) & (1u64 << state.wqueen() | 1u64 << state.bqueen()) != 0
+}
+
+/// Proven results score as wins, otherwise the difference between the
+/// longest paths found so far.
+fn separated_score(solved: &SeparatedResult) -> f64 {
+    match solved.winner {
+        Some(true) => INFINITY,
+        Some(false) => -INFINITY,
+        None => solved.white.lower as f64 - solved.black.lower as f64,
+    }
+}
+
+pub fn get_children(state: &GameState) -> Vec<(GameState, u8)> {
+    let legal_moves = get_possible_legal_moves(state);
+    let mut children = Vec::new();
+    for i in 0..64 {
+        if (legal_moves >> i) & 1 == 1 {
+            let mut child_state = *state;
+            child_state.make_move(i as u8);
+            children.push((child_state, i as u8));
+        }
+    }
+    children
+}
+
+#[derive(Clone)]
+pub struct Searcher<E, L> {
+    pub evaluator: E,
+    pub limit: L,
+    /// Score captures in reach and separated queens exactly instead of
+    /// searching on.
+    pub solve_endings: bool,
+    /// Start from a random move order instead of by square, before
+    /// `ordering` sorts the moves.
+    pub shuffle_moves: bool,
+    pub ordering: MoveOrdering,
+    pub tt: Option<Arc<TranspositionTable>>,
+}
+
+struct Run<'a, E, L> {
+    evaluator: &'a E,
+    limit: &'a L,
+    solve_endings: bool,
+    shuffle_moves: bool,
+    ordering: MoveOrdering,
+    tables: &'a mut OrderingTables,
+    tt: Option<&'a TranspositionTable>,
+    nodes: u64,
+    next_stop_check: u64,
+    stopped: bool,
+    tt_probes: u64,
+    tt_hits: u64,
+}
+
+impl<E: Evaluator, L: SearchLimit> Run<'_, E, L> {
+    fn is_settled(&self, state: &GameState) -> bool {
+        if state.result().is_some() { return true; }
+        if !self.solve_endings { return false; }
+        queens_in_reach(state) || !queens_share_region(state)
+    }
+
+    fn leaf_score(&self, state: &GameState) -> f64 {
+        if state.result() == Some(true) {
+            return INFINITY;
+        }
+        if state.result() == Some(false) {
+            return -INFINITY;
+        }
+        if self.solve_endings {
+            if queens_in_reach(state) {
+                return if state.is_white_turn() { INFINITY } else { -INFINITY };
+            }
+            if let Some(solved) = solve_separated(state, LEAF_NODE_LIMIT) {
+                return separated_score(&solved);
+            }
+        }
+        self.evaluator.evaluate(state)
+    }
+
+    /// Returns the score, the best move, the unused budget and whether the
+    /// node was cut off. `ply` counts the moves from the root.
+    fn minimax(
+        &mut self,
+        state: &GameState,
+        budget: L::Budget,
+        alpha: f64, beta: f64,
+        ply: usize
+    ) -> (f64, Option<u8>, L::Budget, bool) {
+        let top_level = ply == 0;
+        self.nodes += 1;
+        let limit = self.limit;
+        if (limit.is_leaf(budget) || self.is_settled(state)) && !top_level {
+            return (self.leaf_score(state), None, limit.leaf_left_over(budget), false);
+        }
+        if self.nodes >= self.next_stop_check {
+            self.next_stop_check = self.nodes + STOP_CHECK_INTERVAL;
+            self.stopped = limit.should_stop();
+        }
+        if self.stopped {
+            return (0., None, budget, true);
+        }
+
+        let key = state.zobrist();
+        let mut tt_move = None;
+        if let Some(tt) = self.tt {
+            self.tt_probes += 1;
+            if let Some(entry) = tt.probe(key) {
+                self.tt_hits += 1;
+                tt_move = entry.best_move;
+                let cutoff = match entry.bound {
+                    Bound::Exact => true,
+                    Bound::Lower => entry.score >= beta,
+                    Bound::Upper => entry.score <= alpha,
+                };
+                if cutoff && !top_level && entry.draft >= limit.draft(budget) {
+                    return (entry.score, entry.best_move, limit.leaf_left_over(budget), entry.bound != Bound::Exact);
+                }
+            }
+        }
+
+        let (alpha_start, beta_start) = (alpha, beta);
+        let mut alpha = alpha;
+        let mut beta = beta;
+
+        let mut best_value = if state.is_white_turn() { -INFINITY } else { INFINITY };
+        let mut best_move = None;
+        let mut best_move_unpruned = None;
+        let mut pruned = false;
+
+        let mut remaining = budget;
+
+        let mut children = get_children(state);
+        if self.shuffle_moves {
+            with_rng(|rng| children.shuffle(rng));
+        }
+        self.tables.order(self.ordering, state, &mut children, tt_move, ply);
+        let count = children.len();
+
+        for (i, (child, move_made)) in children.into_iter().enumerate() {
+            let given = limit.child_budget(remaining, count - i);
+            let (mut value, _, left_over, eval_pruned)
+                = self.minimax(&child, given, alpha, beta, ply + 1);
+            remaining = limit.charge(remaining, given, left_over);
+            if self.stopped {
+                break;
+            }
+            // Prefer quicker wins and slower losses.
+            if value > 0. { value -= 0.01 } else { value += 0.01 }
+            if state.is_white_turn() {
+                if value >= best_value {
+                    best_value = value;
+                    if !eval_pruned { best_move = Some(move_made) };
+                    best_move_unpruned = Some(move_made);
+                }
+                alpha = alpha.max(value);
+            } else {
+                if value <= best_value {
+                    best_value = value;
+                    if !eval_pruned { best_move = Some(move_made) };
+                    best_move_unpruned = Some(move_made);
+                }
+                beta = beta.min(value);
+            }
+            if beta <= alpha {
+                self.tables.record_cutoff(state, move_made, ply, limit.draft(budget));
+                pruned = true;
+                break;
+            }
+        }
+
+        if best_move.is_none() {
+            best_move = best_move_unpruned;
+        }
+
+        if let Some(tt) = self.tt
+            && !self.stopped
+        {
+            let bound = if best_value <= alpha_start {
+                Bound::Upper
+            } else if best_value >= beta_start {
+                Bound::Lower
+            } else {
+                Bound::Exact
+            };
+            tt.store(key, TtEntry { draft: limit.draft(budget), bound, score: best_value, best_move });
+        }
+
+        // The noise goes on after the table entry, which must keep the
+        // bound the search actually proved.
+        let noise: f64 = with_rng(|rng| rng.gen_range(-0.001..0.001));
+        best_value += noise;
+
+        (best_value, best_move, remaining, pruned)
+    }
+}
+
+impl<E: Evaluator, L: SearchLimit> Searcher<E, L> {
+    /// A searcher that solves endings and uses every move ordering
+    /// heuristic.
+    pub fn new(evaluator: E, limit: L) -> Self {
+        Self {
+            evaluator,
+            limit,
+            solve_endings: true,
+            shuffle_moves: false,
+            ordering: MoveOrdering::ALL,
+            tt: None,
+        }
+    }
+
+    pub fn solve_endings(mut self, solve_endings: bool) -> Self {
+        self.solve_endings = solve_endings;
+        self
+    }
+
+    pub fn shuffle_moves(mut self, shuffle_moves: bool) -> Self {
+        self.shuffle_moves = shuffle_moves;
+        self
+    }
+
+    pub fn ordering(mut self, ordering: MoveOrdering) -> Self {
+        self.ordering = ordering;
+        self
+    }
+
+    /// Caches results in `tt`, which may already hold some from earlier
+    /// searches.
+    pub fn tt(mut self, tt: Option<Arc<TranspositionTable>>) -> Self {
+        self.tt = tt;
+        self
+    }
+
+    fn search_with<M: SearchLimit>(
+        &self,
+        state: &GameState,
+        limit: &M,
+        tables: &mut OrderingTables
+    ) -> SearchResult {
+        let mut run = Run {
+            evaluator: &self.evaluator,
+            limit,
+            solve_endings: self.solve_endings,
+            shuffle_moves: self.shuffle_moves,
+            ordering: self.ordering,
+            tables,
+            tt: self.tt.as_deref(),
+            nodes: 0,
+            next_stop_check: STOP_CHECK_INTERVAL,
+            stopped: false,
+            tt_probes: 0,
+            tt_hits: 0,
+        };
+        let (score, best_move, _, _) =
+            run.minimax(state, limit.root_budget(), -INFINITY, INFINITY, 0);
+        SearchResult {
+            score,
+            best_move,
+            nodes: run.nodes,
+            complete: !run.stopped,
+            tt_probes: run.tt_probes,
+            tt_hits: run.tt_hits,
+        }
+    }
+
+    /// Searches `state`, which must not be over. The root is always
+    /// expanded, whatever the limit.
+    pub fn search(&self, state: &GameState) -> SearchResult {
+        self.search_with(state, &self.limit, &mut OrderingTables::new())
+    }
+
+    /// The exact solver's verdict when the queens are separated and it
+    /// finds a move in time, timed from `start`.
+    pub fn solved_info(&self, state: &GameState, start: Instant) -> Option<SearchInfo> {
+        if !self.solve_endings {
+            return None;
+        }
+        self.solved_info_from(solve_separated(state, ROOT_NODE_LIMIT)?, start)
+    }
+
+    /// `solved_info` with the solver cut off at `deadline`.
+    pub fn solved_info_by(&self, state: &GameState, start: Instant, deadline: Instant) -> Option<SearchInfo> {
+        if !self.solve_endings {
+            return None;
+        }
+        self.solved_info_from(solve_separated_by(state, ROOT_NODE_LIMIT, deadline)?, start)
+    }
+
+    fn solved_info_from(&self, solved: SeparatedResult, start: Instant) -> Option<SearchInfo> {
+        let best_move = solved.best_move?;
+        Some(SearchInfo {
+            best_move,
+            score: Some(separated_score(&solved)),
+            pv: vec![best_move],
+            depth: None,
+            nodes: 0,
+            time: start.elapsed(),
+            proven: solved.winner.is_some(),
+        })
+    }
+
+    /// The exact solver's move when the queens are separated and it finds
+    /// one in time.
+    pub fn solved_move(&self, state: &GameState) -> Option<u8> {
+        self.solved_info(state, Instant::now()).map(|info| info.best_move)
+    }
+
+    /// The line from `state` that starts with `first` and goes on with
+    /// the best moves the transposition table remembers.
+    pub fn principal_variation(&self, state: &GameState, first: Option<u8>) -> Vec<u8> {
+        let mut pv = Vec::new();
+        let mut state = *state;
+        let mut next = first;
+        while let Some(to) = next {
+            if state.result().is_some() || get_possible_legal_moves(&state) & 1 << to == 0 {
+                break;
+            }
+            state.make_move(to);
+            pv.push(to);
+            next = self.tt.as_ref()
+                .and_then(|tt| tt.probe(state.zobrist()))
+                .and_then(|entry| entry.best_move);
+        }
+        pv
+    }
+
+    /// `result`, from a search of `state` that finished `depth` plies,
+    /// as a bot reports it.
+    pub fn info(&self, state: &GameState, result: &SearchResult, depth: Option<u32>, time: Duration) -> SearchInfo {
+        SearchInfo {
+            best_move: result.best_move.unwrap(),
+            score: Some(result.score),
+            pv: self.principal_variation(state, result.best_move),
+            depth,
+            nodes: result.nodes,
+            time,
+            proven: result.is_proven(),
+        }
+    }
+
+    /// `best_move` with what the search found.
+    pub fn best_move_info(&self, state: &GameState) -> SearchInfo {
+        let start = Instant::now();
+        if let Some(info) = self.solved_info(state, start) {
+            return info;
+        }
+        let result = self.search(state);
+        self.info(state, &result, self.limit.depth(), start.elapsed())
+    }
+
+    /// The move to play in `state`, which must not be over. With separated
+    /// queens the exact solver picks it when it can.
+    pub fn best_move(&self, state: &GameState) -> u8 {
+        self.best_move_info(state).best_move
+    }
+}
+
+impl<E: Evaluator> Searcher<E, DepthLimit> {
+    /// Iterative deepening: searches one ply deeper at a time, up to the
+    /// depth limit, until `deadline` passes or the result is proven.
+    /// Returns the result of the deepest search that finished, with the
+    /// node and table counts of all of them, and its depth. Depth 1 always
+    /// finishes.
+    pub fn deepen(&self, state: &GameState, deadline: Instant) -> (SearchResult, u32) {
+        self.deepen_from(state, deadline, 1, &AtomicBool::new(false))
+    }
+
+    /// `deepen` starting at `first` plies, which always finishes, and
+    /// giving up on deeper searches once `stop` is set.
+    fn deepen_from(&self, state: &GameState, deadline: Instant, first: u32, stop: &AtomicBool) -> (SearchResult, u32) {
+        let mut tables = OrderingTables::new();
+        let mut result = self.search_with(state, &DepthLimit(first), &mut tables);
+        let (mut nodes, mut tt_probes, mut tt_hits) = (result.nodes, result.tt_probes, result.tt_hits);
+        let mut reached = first;
+        for depth in first + 1..=self.limit.0 {
+            if result.is_proven() || Instant::now() >= deadline {
+                break;
+            }
+            let limit = TimeLimit { inner: StopFlag { inner: DepthLimit(depth), stop }, deadline };
+            let next = self.search_with(state, &limit, &mut tables);
+            nodes += next.nodes;
+            tt_probes += next.tt_probes;
+            tt_hits += next.tt_hits;
+            if !next.complete {
+                break;
+            }
+            result = next;
+            reached = depth;
+        }
+        result.nodes = nodes;
+        result.tt_probes = tt_probes;
+        result.tt_hits = tt_hits;
+        (result, reached)
+    }
+
+    /// `deepen` on `threads` threads sharing the transposition table
+    /// (Lazy SMP). The helper threads run the same search, half of them
+    /// one ply ahead, and only leave results in the table for this thread
+    /// to find; they stop when it finishes. The returned counts include
+    /// theirs. Without a table, or with one thread, this is `deepen`.
+    pub fn deepen_parallel(&self, state: &GameState, deadline: Instant, threads: usize) -> (SearchResult, u32) {
+        if threads <= 1 || self.tt.is_none() {
+            return self.deepen(state, deadline);
+        }
+        let seeds: Vec<u64> = with_rng(|rng| (1..threads).map(|_| next_seed(rng)).collect());
+        let stop = AtomicBool::new(false);
+        thread::scope(|scope| {
+            let helpers: Vec<_> = seeds
+                .into_iter()
+                .enumerate()
+                .map(|(i, seed)| {
+                    let stop = &stop;
+                    scope.spawn(move || {
+                        seed_thread(seed);
+                        let first = if i % 2 == 0 { 2 } else { 1 };
+                        self.deepen_from(state, deadline, first.min(self.limit.0.max(1)), stop).0
+                    })
+                })
+                .collect();
+            let (mut result, reached) = self.deepen_from(state, deadline, 1, &stop);
+            stop.store(true, Ordering::Relaxed);
+            for helper in helpers {
+                let helper = helper.join().unwrap();
+                result.nodes += helper.nodes;
+                result.tt_probes += helper.tt_probes;
+                result.tt_hits += helper.tt_hits;
+            }
+            (result, reached)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::eval::Mobility;
+    use crate::qd::utils::vgs;
+    use crate::rng::{rng_from_seed, seed_thread};
+    use rand::Rng;
+
+    #[test]
+    fn test_takes_queen() {
+        let state = vgs("
+            ...B....
+            ........
+            ........
+            ........
+            ........
+            ........
+            ........
+            ...W....
+        ", true);
+        for searcher in [
+            Searcher::new(Mobility, DepthLimit(3)),
+            Searcher::new(Mobility, DepthLimit(3)).solve_endings(false),
+        ] {
+            let result = searcher.search(&state);
+            assert_eq!(result.best_move, Some(59));
+            assert!(result.score > INFINITY / 2.);
+        }
+        let result = Searcher::new(Mobility, NodeBudget(64)).shuffle_moves(true).search(&state);
+        assert_eq!(result.best_move, Some(59));
+    }
+
+    #[test]
+    fn test_limits_bound_the_search() {
+        seed_thread(61);
+        let state = GameState::def_rand_with(&mut rng_from_seed(Some(61)));
+        let shallow = Searcher::new(Mobility, DepthLimit(1)).search(&state);
+        let deep = Searcher::new(Mobility, DepthLimit(2)).search(&state);
+        assert_eq!(shallow.nodes, 1 + get_children(&state).len() as u64);
+        assert!(deep.nodes > shallow.nodes);
+
+        let small = Searcher::new(Mobility, NodeBudget(64)).search(&state);
+        let large = Searcher::new(Mobility, NodeBudget(4096)).search(&state);
+        assert!(small.nodes < large.nodes);
+
+        assert!(shallow.complete && small.complete);
+        let stopped = Searcher::new(Mobility, TimeLimit::new(DepthLimit(20), Duration::ZERO)).search(&state);
+        assert!(!stopped.complete);
+        assert!(stopped.nodes < 2 * STOP_CHECK_INTERVAL);
+    }
+
+    #[test]
+    fn test_deepen() {
+        seed_thread(62);
+        let state = GameState::def_rand_with(&mut rng_from_seed(Some(62)));
+        let searcher = Searcher::new(Mobility, DepthLimit(MAX_DEPTH));
+        let (result, depth) = searcher.deepen(&state, Instant::now());
+        assert_eq!(depth, 1);
+        assert!(result.complete && result.best_move.is_some());
+
+        let (result, depth) = Searcher::new(Mobility, DepthLimit(3))
+            .deepen(&state, Instant::now() + Duration::from_secs(60));
+        assert_eq!(depth, 3);
+        let fixed = Searcher::new(Mobility, DepthLimit(3)).search(&state);
+        assert!(result.nodes > fixed.nodes);
+
+        // Stops soon after the deadline with the last full depth. The bound
+        // on the time is loose so a busy machine does not fail the test.
+        let start = Instant::now();
+        let (result, depth) = searcher.deepen(&state, start + Duration::from_millis(50));
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(depth < MAX_DEPTH);
+        assert!(result.complete && result.best_move.is_some());
+    }
+
+    #[test]
+    fn test_tt_keeps_proven_results() {
+        let mut rng = rng_from_seed(Some(63));
+        for _ in 0..100 {
+            let mut state = GameState::def_rand_with(&mut rng);
+            while (!state.blocks()).count_ones() > 14 && state.result().is_none() {
+                let moves = get_children(&state);
+                state = moves[rng.gen_range(0..moves.len())].0;
+            }
+            if state.result().is_some() {
+                continue;
+            }
+            let plain = Searcher::new(Mobility, DepthLimit(MAX_DEPTH)).solve_endings(false).search(&state);
+            let tt = Arc::new(TranspositionTable::new(1));
+            let cached = Searcher::new(Mobility, DepthLimit(MAX_DEPTH))
+                .solve_endings(false)
+                .tt(Some(tt))
+                .search(&state);
+            assert!(plain.is_proven() && cached.is_proven(), "{}", state);
+            assert_eq!(plain.score > 0., cached.score > 0., "{}", state);
+            assert!(cached.tt_probes > 0);
+        }
+    }
+
+    #[test]
+    fn test_tt_stores_score_without_noise() {
+        let mut rng = rng_from_seed(Some(66));
+        for _ in 0..20 {
+            let state = GameState::def_rand_with(&mut rng);
+            let stored = |seed| {
+                seed_thread(seed);
+                let tt = Arc::new(TranspositionTable::new(1));
+                let result = Searcher::new(Mobility, DepthLimit(1)).tt(Some(tt.clone())).search(&state);
+                let entry = tt.probe(state.zobrist()).unwrap();
+                assert_eq!(entry.bound, Bound::Exact);
+                assert!((entry.score - result.score).abs() <= 0.001);
+                entry.score
+            };
+            assert_eq!(stored(1), stored(2), "{}", state);
+        }
+    }
+
+    #[test]
+    fn test_tt_saves_nodes_when_deepening() {
+        let state = GameState::from_notation("3Bx3/8/8/1x4xx/7x/8/2x5/4W2x w").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(60);
+        // Killers and history would hide what the table saves.
+        let ordering = MoveOrdering { tt_move: true, ..MoveOrdering::NONE };
+        seed_thread(64);
+        let (plain, _) = Searcher::new(Mobility, DepthLimit(6)).ordering(ordering).deepen(&state, deadline);
+        seed_thread(64);
+        let tt = Arc::new(TranspositionTable::new(4));
+        let (cached, _) = Searcher::new(Mobility, DepthLimit(6))
+            .ordering(ordering)
+            .tt(Some(tt))
+            .deepen(&state, deadline);
+        assert!(cached.nodes < plain.nodes);
+        assert!(cached.tt_hit_rate().unwrap() > 0.);
+        assert_eq!(plain.tt_hit_rate(), None);
+    }
+
+    #[test]
+    fn test_ordering_saves_nodes() {
+        let state = GameState::from_notation("3Bx3/8/8/1x4xx/7x/8/2x5/4W2x w").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(60);
+        let nodes = |ordering| {
+            seed_thread(65);
+            let tt = Arc::new(TranspositionTable::new(4));
+            Searcher::new(Mobility, DepthLimit(6)).ordering(ordering).tt(Some(tt)).deepen(&state, deadline).0.nodes
+        };
+        assert!(nodes(MoveOrdering::ALL) * 2 < nodes(MoveOrdering::NONE));
+    }
+
+    #[test]
+    fn test_deepen_parallel() {
+        let state = GameState::from_notation("3Bx3/8/8/1x4xx/7x/8/2x5/4W2x w").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(60);
+        let searcher = Searcher::new(Mobility, DepthLimit(5)).tt(Some(Arc::new(TranspositionTable::new(4))));
+        let (parallel, depth) = searcher.deepen_parallel(&state, deadline, 3);
+        assert_eq!(depth, 5);
+        assert!(parallel.complete);
+        assert!(get_possible_legal_moves(&state) & 1 << parallel.best_move.unwrap() != 0);
+
+        let stopped = searcher.deepen_parallel(&state, Instant::now(), 3);
+        assert_eq!(stopped.1, 1);
+    }
+
+    #[test]
+    fn test_best_move_info() {
+        let state = GameState::from_notation("3Bx3/8/8/1x4xx/7x/8/2x5/4W2x w").unwrap();
+        let searcher = Searcher::new(Mobility, DepthLimit(4)).tt(Some(Arc::new(TranspositionTable::new(1))));
+        let info = searcher.best_move_info(&state);
+        assert_eq!(info.depth, Some(4));
+        assert_eq!(info.pv[0], info.best_move);
+        assert!(info.pv.len() > 1 && info.pv.len() <= 4);
+        let mut line = state;
+        for &to in &info.pv {
+            line.make_move(to);
+        }
+        let plain = Searcher::new(Mobility, NodeBudget(256)).best_move_info(&state);
+        assert_eq!(plain.pv, vec![plain.best_move]);
+        assert_eq!(plain.depth, None);
+    }
+
+    #[test]
+    fn test_deepen_stops_when_proven() {
+        let state = vgs("
+            ...B....
+            ........
+            ........
+            ........
+            ........
+            ........
+            ........
+            ...W....
+        ", true);
+        let (result, depth) = Searcher::new(Mobility, DepthLimit(MAX_DEPTH))
+            .deepen(&state, Instant::now() + Duration::from_secs(60));
+        assert_eq!(depth, 1);
+        assert_eq!(result.best_move, Some(59));
+    }
+}
@@ -0,0 +1,287 @@
+//! Exact play once the queens are cut off from each other.
+//!
+//! When no path of empty squares joins the queens, neither can ever capture
+//! the other and each side just moves around its own region. Every move
+//! blocks the square left behind, so a side can make as many moves as the
+//! longest queen-move path from its square, and the side to move wins
+//! exactly when its longest path is longer than the opponent's.
+
+use std::time::Instant;
+use crate::qd::magic::queen_attacks;
+use crate::qd::regions::{flood_fill, queens_share_region, reachable};
+use crate::qd::state::GameState;
+
+/// Node budget for a solve at the root of a search.
+pub const ROOT_NODE_LIMIT: u64 = 200_000;
+/// Node budget for a solve at a search leaf.
+pub const LEAF_NODE_LIMIT: u64 = 2_000;
+/// Nodes between deadline checks.
+const DEADLINE_CHECK_INTERVAL: u64 = 1024;
+
+/// Bounds on the number of moves a queen can still make.
+#[derive(PartialEq)]
+#[derive(Debug, Eq)]
+#[derive(Copy, Clone)]
+pub struct PathBounds {
+    pub lower: u32,
+    pub upper: u32,
+}
+
+impl PathBounds {
+    pub fn is_exact(&self) -> bool {
+        self.lower == self.upper
+    }
+}
+
+#[derive(PartialEq)]
+#[derive(Debug, Eq)]
+#[derive(Copy, Clone)]
+pub struct SeparatedResult {
+    /// The winner, if the bounds prove it.
+    pub winner: Option<bool>,
+    /// First move of the longest path found for the side to move.
+    pub best_move: Option<u8>,
+    pub white: PathBounds,
+    pub black: PathBounds,
+}
+
+struct PathSearch {
+    nodes: u64,
+    node_limit: u64,
+    deadline: Option<Instant>,
+    best: u32,
+    best_first: Option<u8>,
+    upper: u32,
+}
+
+impl PathSearch {
+    fn onward_moves(square: u8, free: u64) -> u64 {
+        queen_attacks(square, !free) & free
+    }
+
+    /// Depth-first search for a longer path, pruned by the number of free
+    /// squares still connected to the queen. Returns false once the node
+    /// budget or the time runs out.
+    fn search(&mut self, square: u8, free: u64, length: u32, first: Option<u8>) -> bool {
+        if length > self.best {
+            self.best = length;
+            self.best_first = first;
+        }
+        if self.best == self.upper {
+            return true;
+        }
+        self.nodes += 1;
+        if self.nodes > self.node_limit {
+            return false;
+        }
+        if self.nodes.is_multiple_of(DEADLINE_CHECK_INTERVAL)
+            && self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return false;
+        }
+        if length + flood_fill(1u64 << square, free).count_ones() <= self.best {
+            return true;
+        }
+
+        // Squares with fewer onward moves first: long paths tend to clear
+        // out dead ends before they get cut off.
+        let mut children = Vec::new();
+        let mut rest = Self::onward_moves(square, free);
+        while rest != 0 {
+            let to = rest.trailing_zeros() as u8;
+            rest &= rest - 1;
+            let onward = Self::onward_moves(to, free & !(1u64 << to)).count_ones();
+            children.push((onward, to));
+        }
+        children.sort_unstable();
+
+        for (_, to) in children {
+            if !self.search(to, free & !(1u64 << to), length + 1, first.or(Some(to))) {
+                return false;
+            }
+            if self.best == self.upper {
+                return true;
+            }
+        }
+        true
+    }
+}
+
+/// Bounds on the longest path of queen moves from `queen` through the
+/// `free` squares, with the first move of the longest path found.
+pub fn longest_path(queen: u8, free: u64, node_limit: u64) -> (PathBounds, Option<u8>) {
+    longest_path_by(queen, free, node_limit, None)
+}
+
+fn longest_path_by(queen: u8, free: u64, node_limit: u64, deadline: Option<Instant>) -> (PathBounds, Option<u8>) {
+    let upper = flood_fill(1u64 << queen, free).count_ones();
+    let mut search = PathSearch { nodes: 0, node_limit, deadline, best: 0, best_first: None, upper };
+    let complete = search.search(queen, free, 0, None);
+    let upper = if complete { search.best } else { upper };
+    (PathBounds { lower: search.best, upper }, search.best_first)
+}
+
+/// Solves `state` if its queens are separated. Returns `None` while they
+/// can still meet.
+pub fn solve_separated(state: &GameState, node_limit: u64) -> Option<SeparatedResult> {
+    solve(state, node_limit, None)
+}
+
+/// Like `solve_separated`, but also gives up at `deadline`, leaving the
+/// bounds found so far.
+pub fn solve_separated_by(state: &GameState, node_limit: u64, deadline: Instant) -> Option<SeparatedResult> {
+    solve(state, node_limit, Some(deadline))
+}
+
+fn solve(state: &GameState, node_limit: u64, deadline: Option<Instant>) -> Option<SeparatedResult> {
+    if queens_share_region(state) {
+        return None;
+    }
+    let (white, white_move) = longest_path_by(state.wqueen(), reachable(state, state.wqueen()), node_limit, deadline);
+    let (black, black_move) = longest_path_by(state.bqueen(), reachable(state, state.bqueen()), node_limit, deadline);
+    let (mover, other, best_move) = if state.is_white_turn() {
+        (white, black, white_move)
+    } else {
+        (black, white, black_move)
+    };
+    let winner = if mover.lower > other.upper {
+        Some(state.is_white_turn())
+    } else if mover.upper <= other.lower {
+        Some(!state.is_white_turn())
+    } else {
+        None
+    };
+    Some(SeparatedResult { winner, best_move, white, black })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qd::startpos::forced_result;
+    use crate::qd::utils::vgs;
+    use crate::rng::rng_from_seed;
+    use rand::Rng;
+
+    fn longest_path_brute(square: u8, free: u64) -> u32 {
+        let mut best = 0;
+        let mut rest = queen_attacks(square, !free) & free;
+        while rest != 0 {
+            let to = rest.trailing_zeros() as u8;
+            rest &= rest - 1;
+            best = best.max(1 + longest_path_brute(to, free & !(1u64 << to)));
+        }
+        best
+    }
+
+    fn random_separated(rng: &mut impl Rng) -> GameState {
+        loop {
+            let mut blocks = 0u64;
+            for _ in 0..rng.gen_range(40..70) {
+                blocks |= 1 << rng.gen_range(0..64);
+            }
+            let wqueen = rng.gen_range(0..64u8);
+            let bqueen = (wqueen + rng.gen_range(1..64u8)) % 64;
+            blocks &= !(1u64 << wqueen) & !(1u64 << bqueen);
+            let state = GameState::new(Some(wqueen), Some(bqueen), Some(blocks), Some(rng.gen_bool(0.5)));
+            if !queens_share_region(&state) && (!state.blocks()).count_ones() < 24 {
+                return state;
+            }
+        }
+    }
+
+    #[test]
+    fn test_longest_path_matches_brute_force() {
+        let mut rng = rng_from_seed(Some(21));
+        for _ in 0..300 {
+            let state = random_separated(&mut rng);
+            let free = reachable(&state, state.wqueen());
+            let (bounds, first) = longest_path(state.wqueen(), free, u64::MAX);
+            assert!(bounds.is_exact());
+            assert_eq!(bounds.lower, longest_path_brute(state.wqueen(), free), "{}", state);
+            if let Some(first) = first {
+                assert_eq!(1 + longest_path_brute(first, free & !(1u64 << first)), bounds.lower);
+            }
+        }
+    }
+
+    #[test]
+    fn test_matches_full_game_tree() {
+        let mut rng = rng_from_seed(Some(22));
+        for _ in 0..200 {
+            let state = random_separated(&mut rng);
+            let solved = solve_separated(&state, u64::MAX).unwrap();
+            let expected = forced_result(&state, 64);
+            assert_eq!(solved.winner, expected, "{}", state);
+            if let Some(to) = solved.best_move {
+                let mut child = state;
+                child.make_move(to);
+                if solved.winner == Some(state.is_white_turn()) {
+                    assert_eq!(forced_result(&child, 64), solved.winner);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_not_separated() {
+        assert_eq!(solve_separated(&GameState::def(), ROOT_NODE_LIMIT), None);
+    }
+
+    #[test]
+    fn test_wall_race() {
+        // White has a 3-square corridor, black a 2-square one: white, to
+        // move, makes the last move.
+        let state = vgs("
+            ########
+            ########
+            ########
+            B..#####
+            ########
+            ########
+            ########
+            W...####
+        ", true);
+        let solved = solve_separated(&state, ROOT_NODE_LIMIT).unwrap();
+        assert_eq!(solved.white, PathBounds { lower: 3, upper: 3 });
+        assert_eq!(solved.black, PathBounds { lower: 2, upper: 2 });
+        assert_eq!(solved.winner, Some(true));
+        assert_eq!(solved.best_move, Some(1));
+    }
+
+    #[test]
+    fn test_node_limit_bounds() {
+        let state = vgs("
+            ...B....
+            ........
+            ........
+            ########
+            ........
+            ........
+            ........
+            ....W...
+        ", true);
+        let solved = solve_separated(&state, 10).unwrap();
+        assert!(solved.white.lower <= solved.white.upper);
+        assert_eq!(solved.white.upper, 31);
+        assert!(solved.best_move.is_some());
+    }
+
+    #[test]
+    fn test_deadline_stops_solver() {
+        let state = vgs("
+            ...B....
+            ........
+            ........
+            ########
+            ........
+            ........
+            ........
+            ....W...
+        ", true);
+        let start = Instant::now();
+        let solved = solve_separated_by(&state, u64::MAX, start).unwrap();
+        assert!(start.elapsed().as_millis() < 100);
+        assert_eq!(solved.white.upper, 31);
+        assert!(solved.best_move.is_some());
+    }
+}
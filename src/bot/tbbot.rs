@@ -0,0 +1,71 @@
+use std::sync::Arc;
+use std::time::Instant;
+use crate::bot::base::{Bot, SearchInfo};
+use crate::bot::search::INFINITY;
+use crate::qd::state::GameState;
+use crate::tablebase::probe::Tablebase;
+
+/// Plays perfectly from the tablebase where it covers the position and
+/// leaves the rest to the wrapped bot.
+#[derive(Clone)]
+pub struct TablebaseBot {
+    inner: Box<dyn Bot>,
+    tablebase: Arc<Tablebase>,
+}
+
+impl TablebaseBot {
+    pub fn new(inner: Box<dyn Bot>, tablebase: Arc<Tablebase>) -> Self {
+        Self { inner, tablebase }
+    }
+
+    /// Wraps `bot` if there is a tablebase, and returns it unchanged if not.
+    pub fn wrap(bot: Box<dyn Bot>, tablebase: &Option<Arc<Tablebase>>) -> Box<dyn Bot> {
+        match tablebase {
+            Some(tablebase) => Box::new(Self::new(bot, tablebase.clone())),
+            None => bot,
+        }
+    }
+}
+
+impl Bot for TablebaseBot {
+    fn decide(&self, state: GameState) -> u8 {
+        match self.tablebase.best_move(&state) {
+            Some((to, _)) => to,
+            None => self.inner.decide(state),
+        }
+    }
+
+    fn decide_with_info(&self, state: GameState) -> SearchInfo {
+        let start = Instant::now();
+        let Some((best_move, entry)) = self.tablebase.best_move(&state) else {
+            return self.inner.decide_with_info(state);
+        };
+        let mut pv = Vec::new();
+        let mut line = state;
+        while let Some((to, _)) = self.tablebase.best_move(&line) {
+            line.make_move(to);
+            pv.push(to);
+        }
+        SearchInfo {
+            best_move,
+            score: Some(if entry.win == state.is_white_turn() { INFINITY } else { -INFINITY }),
+            pv,
+            depth: Some(entry.distance as u32),
+            nodes: 0,
+            time: start.elapsed(),
+            proven: true,
+        }
+    }
+
+    fn new_game(&mut self, start: GameState) {
+        self.inner.new_game(start);
+    }
+
+    fn observe_move(&mut self, state: GameState, to: u8) {
+        self.inner.observe_move(state, to);
+    }
+
+    fn game_over(&mut self, white_won: bool) {
+        self.inner.game_over(white_won);
+    }
+}
@@ -0,0 +1,239 @@
+//! Transposition table for the alpha-beta search.
+//!
+//! Each slot is three atomic words: the key mixed with both data words,
+//! then the data. Threads read and write slots without locking; a slot
+//! torn by a concurrent write fails the key check and reads as a miss.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Size of one slot in bytes.
+pub const SLOT_BYTES: usize = 24;
+/// Table size the bots use unless told otherwise, in megabytes.
+pub const DEFAULT_TT_MB: usize = 1;
+
+const NO_MOVE: u64 = 0xff;
+/// Set in every written slot, so that zeroed slots never match.
+const FILLED: u64 = 1 << 63;
+
+/// What the stored score says about the true score.
+#[derive(PartialEq)]
+#[derive(Debug, Eq)]
+#[derive(Copy, Clone)]
+pub enum Bound {
+    Exact,
+    /// The true score is at least the stored one.
+    Lower,
+    /// The true score is at most the stored one.
+    Upper,
+}
+
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Copy, Clone)]
+pub struct TtEntry {
+    /// How much search is behind the score, as given by
+    /// `SearchLimit::draft`.
+    pub draft: u8,
+    pub bound: Bound,
+    pub score: f64,
+    pub best_move: Option<u8>,
+}
+
+impl TtEntry {
+    fn pack(&self) -> (u64, u64) {
+        let bound = match self.bound {
+            Bound::Exact => 0,
+            Bound::Lower => 1,
+            Bound::Upper => 2,
+        };
+        let best_move = self.best_move.map_or(NO_MOVE, u64::from);
+        (self.score.to_bits(), FILLED | bound << 16 | best_move << 8 | self.draft as u64)
+    }
+
+    fn unpack(score: u64, info: u64) -> Self {
+        let bound = match (info >> 16) & 0b11 {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper,
+        };
+        let best_move = (info >> 8) & 0xff;
+        Self {
+            draft: info as u8,
+            bound,
+            score: f64::from_bits(score),
+            best_move: if best_move == NO_MOVE { None } else { Some(best_move as u8) },
+        }
+    }
+}
+
+#[derive(Default)]
+struct Slot {
+    check: AtomicU64,
+    score: AtomicU64,
+    info: AtomicU64,
+}
+
+/// A fixed-size hash table of search results keyed by zobrist hash. A
+/// slot is overwritten by any other position, and by the same position
+/// unless the stored result has more search behind it.
+pub struct TranspositionTable {
+    slots: Box<[Slot]>,
+    mask: u64,
+}
+
+impl TranspositionTable {
+    /// A table of at most `megabytes` megabytes, rounded down to a power
+    /// of two slots. Always has at least one slot.
+    pub fn new(megabytes: usize) -> Self {
+        let wanted = (megabytes * 1024 * 1024 / SLOT_BYTES).max(1);
+        let len = 1usize << wanted.ilog2();
+        Self {
+            slots: (0..len).map(|_| Slot::default()).collect(),
+            mask: len as u64 - 1,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    fn slot(&self, key: u64) -> &Slot {
+        &self.slots[(key & self.mask) as usize]
+    }
+
+    pub fn probe(&self, key: u64) -> Option<TtEntry> {
+        let slot = self.slot(key);
+        let check = slot.check.load(Ordering::Relaxed);
+        let score = slot.score.load(Ordering::Relaxed);
+        let info = slot.info.load(Ordering::Relaxed);
+        if info & FILLED == 0 || check ^ score ^ info != key {
+            return None;
+        }
+        Some(TtEntry::unpack(score, info))
+    }
+
+    pub fn store(&self, key: u64, entry: TtEntry) {
+        if let Some(old) = self.probe(key)
+            && old.draft > entry.draft
+        {
+            return;
+        }
+        let slot = self.slot(key);
+        let (score, info) = entry.pack();
+        slot.check.store(key ^ score ^ info, Ordering::Relaxed);
+        slot.score.store(score, Ordering::Relaxed);
+        slot.info.store(info, Ordering::Relaxed);
+    }
+
+    /// Empties every slot.
+    pub fn clear(&self) {
+        for slot in self.slots.iter() {
+            slot.check.store(0, Ordering::Relaxed);
+            slot.score.store(0, Ordering::Relaxed);
+            slot.info.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+/// A bot's table size and the table it keeps for the game it is playing.
+/// Outside a game every search gets a fresh table.
+#[derive(Clone)]
+pub struct GameTable {
+    tt_mb: usize,
+    game_tt: Option<Arc<TranspositionTable>>,
+}
+
+impl GameTable {
+    /// Tables of `tt_mb` megabytes, or none for 0.
+    pub fn new(tt_mb: usize) -> Self {
+        Self { tt_mb, game_tt: None }
+    }
+
+    /// The table for the next search.
+    pub fn get(&self) -> Option<Arc<TranspositionTable>> {
+        match &self.game_tt {
+            Some(tt) => Some(tt.clone()),
+            None => self.fresh(),
+        }
+    }
+
+    /// Starts a table to keep from one move of a new game to the next.
+    pub fn new_game(&mut self) {
+        self.game_tt = self.fresh();
+    }
+
+    fn fresh(&self) -> Option<Arc<TranspositionTable>> {
+        (self.tt_mb > 0).then(|| Arc::new(TranspositionTable::new(self.tt_mb)))
+    }
+}
+
+impl Default for GameTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_TT_MB)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(draft: u8, bound: Bound, score: f64, best_move: Option<u8>) -> TtEntry {
+        TtEntry { draft, bound, score, best_move }
+    }
+
+    #[test]
+    fn test_size() {
+        assert_eq!(TranspositionTable::new(0).len(), 1);
+        assert_eq!(TranspositionTable::new(1).len(), 32768);
+        assert!(TranspositionTable::new(16).len() * SLOT_BYTES <= 16 * 1024 * 1024);
+    }
+
+    #[test]
+    fn test_game_table() {
+        let mut table = GameTable::new(1);
+        assert!(!Arc::ptr_eq(&table.get().unwrap(), &table.get().unwrap()));
+        table.new_game();
+        assert!(Arc::ptr_eq(&table.get().unwrap(), &table.get().unwrap()));
+        let mut none = GameTable::new(0);
+        none.new_game();
+        assert!(none.get().is_none());
+    }
+
+    #[test]
+    fn test_store_and_probe() {
+        let tt = TranspositionTable::new(1);
+        let key = 0x1234_5678_9abc_def0;
+        assert_eq!(tt.probe(key), None);
+        assert_eq!(tt.probe(0), None);
+        for stored in [
+            entry(3, Bound::Exact, -12.345, Some(63)),
+            entry(4, Bound::Lower, 1e6 - 0.03, None),
+            entry(9, Bound::Upper, -1e6 + 0.01, Some(0)),
+        ] {
+            tt.store(key, stored);
+            assert_eq!(tt.probe(key), Some(stored));
+        }
+        // A colliding key misses.
+        assert_eq!(tt.probe(key ^ 1 << 40), None);
+        tt.clear();
+        assert_eq!(tt.probe(key), None);
+    }
+
+    #[test]
+    fn test_replacement() {
+        let tt = TranspositionTable::new(0);
+        let deep = entry(5, Bound::Exact, 1., Some(1));
+        tt.store(7, deep);
+        tt.store(7, entry(2, Bound::Exact, 2., Some(2)));
+        assert_eq!(tt.probe(7), Some(deep));
+        let other = entry(1, Bound::Lower, 3., None);
+        tt.store(8, other);
+        assert_eq!(tt.probe(7), None);
+        assert_eq!(tt.probe(8), Some(other));
+    }
+}
@@ -0,0 +1,22 @@
+//! Queen Duel: game rules, bots and rating tournaments.
+//!
+//! [`qd`] holds the rules (positions, move generation, notation and game
+//! records), [`bot`] the bots and the Elo tournament runner, and
+//! [`tablebase`] the solved endgame tables.
+
+pub mod qd;
+pub mod bot;
+pub mod rng;
+pub mod tablebase;
+
+pub use qd::state::GameState;
+pub use qd::game::Game;
+pub use bot::base::Bot;
+pub use bot::collections::map_bot_string;
+pub use bot::registry::parse_bot;
+pub use bot::elo::{run_tournament, run_benchmark, TournamentOptions};
+
+/// Compiles the README examples as doctests.
+#[doc = include_str!("../README.md")]
+#[cfg(doctest)]
+pub struct ReadmeDoctests;
@@ -1,14 +1,20 @@
-mod qd;
-mod bot;
 mod app;
 
-use tokio;
+use std::path::PathBuf;
 use clap::{Parser, Subcommand};
+use qdrust::bot::tt::DEFAULT_TT_MB;
+use qdrust::qd::startpos::StartPositionGenerator;
 use crate::app::enums::ColorMode;
 use crate::app::benchmark::benchmark;
 use crate::app::battle::battle;
+use crate::app::listbots::list_bots;
 use crate::app::playbot::play_bot;
 use crate::app::playbotcli::play_bot_cli;
+use crate::app::replay::replay;
+use crate::app::perft::run_perft;
+use crate::app::search::run_search;
+use crate::app::solve::solve;
+use crate::app::tbgen::tb_gen;
 
 #[derive(Parser, Debug)]
 #[command(name = "qdrust")]
@@ -30,6 +36,12 @@ enum Commands {
         use_token: bool,
         #[arg(long, help = "Automatically open the browser", default_value = "false")]
         open_browser: bool,
+        #[arg(long, help = "Append finished games to this record file")]
+        save: Option<PathBuf>,
+        #[arg(long, help = "Start position generator, e.g. \"blocks=8,symmetry=point,reject=2\"", default_value = "default")]
+        start: StartPositionGenerator,
+        #[arg(long, help = "Let the bot play perfectly in positions this tablebase covers")]
+        tablebase: Option<PathBuf>,
     },
     #[command(about = "Play against a bot (in CLI)")]
     PlayBotCli {
@@ -37,6 +49,14 @@ enum Commands {
         bot_string: String,
         #[arg(long, default_value_t = ColorMode::Random)]
         color: ColorMode,
+        #[arg(long, help = "Start position in Queen Duel notation, e.g. \"3B4/8/8/8/8/8/8/4W3 w\"")]
+        position: Option<String>,
+        #[arg(long, help = "Append the game to this record file")]
+        save: Option<PathBuf>,
+        #[arg(long, help = "Let the bot play perfectly in positions this tablebase covers")]
+        tablebase: Option<PathBuf>,
+        #[arg(long, help = "Seed for reproducible runs")]
+        seed: Option<u64>,
     },
     #[command(about = "Let bots battle and get their eloes", long_about = None)]
     Battle {
@@ -52,6 +72,14 @@ enum Commands {
         k_end: f64,
         #[arg(long, default_value_t = false)]
         sorted: bool,
+        #[arg(long, help = "Append every game to this record file")]
+        save: Option<PathBuf>,
+        #[arg(long, help = "Start position generator, e.g. \"blocks=8,symmetry=point,reject=2\"", default_value = "default")]
+        start: StartPositionGenerator,
+        #[arg(long, help = "Let every bot play perfectly in positions this tablebase covers")]
+        tablebase: Option<PathBuf>,
+        #[arg(long, help = "Seed for reproducible runs")]
+        seed: Option<u64>,
     },
     #[command(about = "Benchmark a bot")]
     Benchmark {
@@ -65,7 +93,78 @@ enum Commands {
         k_start: f64,
         #[arg(long, default_value_t = 32.)]
         k_end: f64,
-    }
+        #[arg(long, help = "Start position generator, e.g. \"blocks=8,symmetry=point,reject=2\"", default_value = "default")]
+        start: StartPositionGenerator,
+        #[arg(long, help = "Let the bot play perfectly in positions this tablebase covers")]
+        tablebase: Option<PathBuf>,
+        #[arg(long, help = "Seed for reproducible runs")]
+        seed: Option<u64>,
+    },
+    #[command(about = "Step through a saved game")]
+    Replay {
+        #[arg(name = "FILE")]
+        path: PathBuf,
+        #[arg(long, help = "Which game in the file to show, starting at 1", default_value_t = 1)]
+        game: usize,
+        #[arg(long, help = "Print every move without waiting", default_value_t = false)]
+        no_pause: bool,
+    },
+    #[command(about = "Count move sequences to a fixed depth")]
+    Perft {
+        #[arg(name = "POSITION", help = "Position in Queen Duel notation, or \"startpos\"")]
+        position: String,
+        #[arg(name = "DEPTH")]
+        depth: u32,
+        #[arg(long, help = "Show the count below each first move", default_value_t = false)]
+        divide: bool,
+    },
+    #[command(about = "Search a position once and print the search statistics")]
+    Search {
+        #[arg(name = "POSITION", help = "Position in Queen Duel notation, or \"startpos\"")]
+        position: String,
+        #[arg(long, help = "Search depth [default: 4], or the deepest iteration with --time")]
+        depth: Option<u32>,
+        #[arg(long, help = "Deepen one ply at a time for this many milliseconds")]
+        time: Option<u64>,
+        #[arg(long, help = "Evaluator, e.g. \"region\" or \"mobility+0.5*region\"", default_value = "mobility")]
+        eval: String,
+        #[arg(long, help = "Transposition table size in megabytes, 0 for none", default_value_t = DEFAULT_TT_MB)]
+        tt_mb: usize,
+        #[arg(long, help = "Deepen to --depth under each move ordering and compare node counts", default_value_t = false)]
+        compare_ordering: bool,
+        #[arg(long, help = "Threads sharing the transposition table", default_value_t = 1)]
+        num_threads: usize,
+    },
+    #[command(about = "Solve every position with few empty squares into a tablebase file")]
+    TbGen {
+        #[arg(name = "FILE")]
+        path: PathBuf,
+        #[arg(long, help = "Largest number of empty squares to cover", default_value_t = 3)]
+        max_empty: u8,
+        #[arg(long, default_value_t = 1)]
+        num_threads: usize,
+    },
+    #[command(about = "Prove who wins a position with proof-number search")]
+    Solve {
+        #[arg(name = "POSITION", help = "Position in Queen Duel notation, \"startpos\", or \"random\" for generated starts")]
+        position: String,
+        #[arg(long, help = "How many starts to solve with \"random\"", default_value_t = 1)]
+        count: usize,
+        #[arg(long, help = "Transposition table size in megabytes", default_value_t = 256)]
+        memory: usize,
+        #[arg(long, help = "Give up after this many expanded nodes")]
+        nodes: Option<u64>,
+        #[arg(long, help = "Start position generator used with \"random\"", default_value = "default")]
+        start: StartPositionGenerator,
+        #[arg(long, help = "Treat positions this tablebase covers as solved")]
+        tablebase: Option<PathBuf>,
+        #[arg(long, help = "Check whether this bot's move keeps the proven result")]
+        check_bot: Option<String>,
+        #[arg(long, help = "Seed for reproducible runs")]
+        seed: Option<u64>,
+    },
+    #[command(about = "List every bot and its parameters")]
+    ListBots,
 }
 
 #[tokio::main]
@@ -73,12 +172,12 @@ async fn main() {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::PlayBot { bot_string, port, use_token, open_browser } => {
-            play_bot(bot_string, port, use_token, open_browser).await
+        Commands::PlayBot { bot_string, port, use_token, open_browser, save, start, tablebase } => {
+            play_bot(bot_string, port, use_token, open_browser, save, start, tablebase).await
                 .expect("Failed to start bot server");
         }
-        Commands::PlayBotCli { bot_string, color } => {
-            play_bot_cli(bot_string, color);
+        Commands::PlayBotCli { bot_string, color, position, save, tablebase, seed } => {
+            play_bot_cli(bot_string, color, position, save, tablebase, seed);
         }
         Commands::Battle { 
             bot_strings, 
@@ -87,8 +186,12 @@ async fn main() {
             k_start,
             k_end,
             sorted,
+            save,
+            start,
+            tablebase,
+            seed,
         } => {
-            battle(bot_strings, num_matchups, num_threads, k_start, k_end, sorted);
+            battle(bot_strings, num_matchups, num_threads, k_start, k_end, sorted, save, start, tablebase, seed);
         }
         Commands::Benchmark {
             bot_string, 
@@ -96,8 +199,29 @@ async fn main() {
             num_threads,
             k_start,
             k_end,
+            start,
+            tablebase,
+            seed,
         } => {
-            benchmark(bot_string, num_matchups, num_threads, k_start, k_end);
+            benchmark(bot_string, num_matchups, num_threads, k_start, k_end, start, tablebase, seed);
+        }
+        Commands::Replay { path, game, no_pause } => {
+            replay(path, game, no_pause);
+        }
+        Commands::Perft { position, depth, divide } => {
+            run_perft(position, depth, divide);
+        }
+        Commands::Search { position, depth, time, eval, tt_mb, compare_ordering, num_threads } => {
+            run_search(position, depth, time, eval, tt_mb, compare_ordering, num_threads);
+        }
+        Commands::TbGen { path, max_empty, num_threads } => {
+            tb_gen(path, max_empty, num_threads);
+        }
+        Commands::Solve { position, count, memory, nodes, start, tablebase, check_bot, seed } => {
+            solve(position, count, memory, nodes, start, tablebase, check_bot, seed);
+        }
+        Commands::ListBots => {
+            list_bots();
         }
     }
 }
\ No newline at end of file
@@ -1,3 +1,13 @@
 pub mod state;
+pub mod error;
 pub mod legalcomp;
-pub mod utils;
\ No newline at end of file
+pub mod utils;
+pub mod magic;
+pub mod game;
+pub mod zobrist;
+pub mod notation;
+pub mod record;
+pub mod perft;
+pub mod startpos;
+pub mod symmetry;
+pub mod regions;
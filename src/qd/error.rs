@@ -0,0 +1,39 @@
+use std::fmt;
+use crate::qd::notation::{square_name, NotationError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QdError {
+    SquareOutOfRange(u8),
+    QueenOnBlock { white: bool },
+    QueensOverlap,
+    IllegalMove(u8),
+    GameOver,
+    BadBlocks(String),
+    Notation(NotationError),
+}
+
+impl fmt::Display for QdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QdError::SquareOutOfRange(square) =>
+                write!(f, "square {} is off the board", square),
+            QdError::QueenOnBlock { white } =>
+                write!(f, "the {} queen stands on a blocked square", if *white { "white" } else { "black" }),
+            QdError::QueensOverlap => write!(f, "both queens stand on the same square"),
+            QdError::IllegalMove(square) =>
+                write!(f, "{} is not a legal move", square_name(*square)),
+            QdError::GameOver => write!(f, "the game is already over"),
+            QdError::BadBlocks(blocks) =>
+                write!(f, "\"{}\" is not a valid block bitboard", blocks),
+            QdError::Notation(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for QdError {}
+
+impl From<NotationError> for QdError {
+    fn from(err: NotationError) -> Self {
+        QdError::Notation(err)
+    }
+}
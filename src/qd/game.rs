@@ -0,0 +1,128 @@
+use crate::qd::error::QdError;
+use crate::qd::state::{GameState, Move, Undo};
+
+/// A game in progress: the starting position, the moves played so far
+/// and the moves that were taken back and can be redone.
+#[derive(Debug, Clone)]
+pub struct Game {
+    start: GameState,
+    state: GameState,
+    history: Vec<Undo>,
+    redo_stack: Vec<Move>,
+}
+
+impl Game {
+    pub fn new(start: GameState) -> Self {
+        Self {
+            start,
+            state: start,
+            history: Vec::new(),
+            redo_stack: Vec::new(),
+        }
+    }
+
+    pub fn start(&self) -> GameState {
+        self.start
+    }
+
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+
+    pub fn moves(&self) -> Vec<Move> {
+        self.history.iter().map(|undo| undo.mv).collect()
+    }
+
+    pub fn result(&self) -> Option<bool> {
+        self.state.result()
+    }
+
+    /// Plays `to` and forgets any moves that could have been redone.
+    pub fn make_move(&mut self, to: u8) {
+        self.try_make_move(to).unwrap_or_else(|err| panic!("{}", err));
+    }
+
+    pub fn try_make_move(&mut self, to: u8) -> Result<Move, QdError> {
+        let undo = self.state.try_make_move(to)?;
+        self.history.push(undo);
+        self.redo_stack.clear();
+        Ok(undo.mv)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.history.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    pub fn undo(&mut self) -> Option<Move> {
+        let undo = self.history.pop()?;
+        self.state.unmake_move(undo);
+        self.redo_stack.push(undo.mv);
+        Some(undo.mv)
+    }
+
+    pub fn redo(&mut self) -> Option<Move> {
+        let mv = self.redo_stack.pop()?;
+        self.history.push(self.state.make_move_undoable(mv.to));
+        Some(mv)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_undo_redo() {
+        let mut game = Game::new(GameState::def());
+        assert!(!game.can_undo());
+        assert_eq!(game.undo(), None);
+
+        game.make_move(3);
+        game.make_move(58);
+        game.make_move(2);
+        let after_3 = game.state();
+        assert_eq!(game.moves(), vec![
+            Move { from: 4, to: 3 },
+            Move { from: 59, to: 58 },
+            Move { from: 3, to: 2 },
+        ]);
+
+        assert_eq!(game.undo(), Some(Move { from: 3, to: 2 }));
+        assert_eq!(game.undo(), Some(Move { from: 59, to: 58 }));
+        assert_eq!(game.moves().len(), 1);
+        assert!(game.can_redo());
+
+        assert_eq!(game.redo(), Some(Move { from: 59, to: 58 }));
+        assert_eq!(game.redo(), Some(Move { from: 3, to: 2 }));
+        assert_eq!(game.redo(), None);
+        assert_eq!(game.state(), after_3);
+
+        while game.undo().is_some() {}
+        assert_eq!(game.state(), game.start());
+    }
+
+    #[test]
+    fn test_try_make_move() {
+        let mut game = Game::new(GameState::def());
+        assert_eq!(game.try_make_move(3), Ok(Move { from: 4, to: 3 }));
+        game.undo();
+        assert_eq!(game.try_make_move(14), Err(QdError::IllegalMove(14)));
+        assert!(game.can_redo());
+        assert!(game.moves().is_empty());
+    }
+
+    #[test]
+    fn test_make_move_clears_redo() {
+        let mut game = Game::new(GameState::def());
+        game.make_move(3);
+        game.undo();
+        assert!(game.can_redo());
+        game.make_move(5);
+        assert!(!game.can_redo());
+        assert_eq!(game.moves(), vec![Move { from: 4, to: 5 }]);
+    }
+}
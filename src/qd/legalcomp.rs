@@ -1,4 +1,5 @@
 use crate::qd::state::{GameState};
+use crate::qd::magic::queen_attacks;
 use std::sync::OnceLock;
 
 static POSSIBLE_ATTACK_MASKS: [OnceLock<u64>; 64] = [const { OnceLock::new() }; 64];
@@ -18,7 +19,7 @@ pub fn get_possible_attack_mask_slow(queen: u8) -> u64 {
             for i in 1..8 {
                 let r = row as i8 + i * dr;
                 let c = col as i8 + i * dc;
-                if r >= 0 && r < 8 && c >= 0 && c < 8 {
+                if (0..8).contains(&r) && (0..8).contains(&c) {
                     mask |= 1 << (r as u8 * 8 + c as u8);
                 } else {
                     break;
@@ -39,7 +40,37 @@ pub fn get_possible_attack_mask(queen: u8) -> u64 {
     })
 }
 
-pub fn get_possible_legal_moves_info_slow(
+pub fn get_possible_legal_moves_info(
+    squeen: u8,
+    oqueen: u8,
+    blocks: u64
+) -> u64 {
+    let oqueen_mask = 1u64 << oqueen;
+    queen_attacks(squeen, blocks | oqueen_mask) & !(blocks & !oqueen_mask)
+}
+
+pub fn get_possible_legal_moves(
+    state: &GameState
+) -> u64 {
+    if state.is_white_turn() {
+        get_possible_legal_moves_info(
+            state.wqueen(),
+            state.bqueen(),
+            state.blocks()
+        )
+    } else {
+        get_possible_legal_moves_info(
+            state.bqueen(),
+            state.wqueen(),
+            state.blocks()
+        )
+    }
+}
+
+/// Legal moves by walking each ray square by square, the reference the
+/// table-driven generator is tested against.
+#[cfg(test)]
+pub(crate) fn get_possible_legal_moves_info_slow(
     squeen: u8,
     oqueen: u8,
     blocks: u64
@@ -58,7 +89,7 @@ pub fn get_possible_legal_moves_info_slow(
                 let r = row as i8 + i * dr;
                 let c = col as i8 + i * dc;
                 let index = r * 8 + c;
-                if !(r >= 0 && r < 8 && c >= 0 && c < 8) {
+                if !((0..8).contains(&r) && (0..8).contains(&c)) {
                     break;
                 }
                 if occupancy & (1 << index) != 0 {
@@ -75,37 +106,11 @@ pub fn get_possible_legal_moves_info_slow(
     res
 }
 
-pub fn get_possible_legal_moves_info(
-    squeen: u8,
-    oqueen: u8,
-    blocks: u64
-) -> u64 {
-    // TODO: magic
-    get_possible_legal_moves_info_slow(squeen, oqueen, blocks)
-}
-
-pub fn get_possible_legal_moves(
-    state: &GameState
-) -> u64 {
-    if state.is_white_turn {
-        get_possible_legal_moves_info(
-            state.wqueen,
-            state.bqueen,
-            state.blocks
-        )
-    } else {
-        get_possible_legal_moves_info(
-            state.bqueen,
-            state.wqueen,
-            state.blocks
-        )
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::qd::utils::*;
+    use rand::Rng;
 
     #[test]
     fn test_get_possible_attack_mask() {
@@ -259,4 +264,45 @@ mod tests {
             ........
         "))
     }
-}
\ No newline at end of file
+    #[test]
+    fn test_get_possible_legal_moves_info_matches_slow() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..20000 {
+            let squeen: u8 = rng.gen_range(0..64);
+            let oqueen: u8 = rng.gen_range(0..64);
+            let blocks: u64 = match rng.gen_range(0..3) {
+                0 => rng.r#gen::<u64>(),
+                1 => rng.r#gen::<u64>() & rng.r#gen::<u64>(),
+                _ => rng.r#gen::<u64>() & rng.r#gen::<u64>() & rng.r#gen::<u64>(),
+            };
+            let blocks = blocks & !(1u64 << squeen);
+            assert_eq!(
+                get_possible_legal_moves_info(squeen, oqueen, blocks),
+                get_possible_legal_moves_info_slow(squeen, oqueen, blocks),
+                "squeen {} oqueen {} blocks {:#x}", squeen, oqueen, blocks
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_possible_legal_moves_info_capturable_queen() {
+        let squeen = 8 * 3 + 5;
+        let oqueen = 8 * 3 + 1;
+        let blocks = vbb("
+            .....#..
+            ........
+            ........
+            ....#...
+            ........
+            ........
+            ...#....
+            .....#..
+        ");
+        assert_eq!(
+            get_possible_legal_moves_info(squeen, oqueen, blocks),
+            get_possible_legal_moves_info_slow(squeen, oqueen, blocks)
+        );
+        assert_ne!(get_possible_legal_moves_info(squeen, oqueen, blocks) & (1 << oqueen), 0);
+        assert_eq!(get_possible_legal_moves_info(squeen, oqueen, blocks) & (1 << (8 * 3)), 0);
+    }
+}
@@ -0,0 +1,261 @@
+use std::sync::OnceLock;
+
+const ROOK_MAGICS: [u64; 64] = [
+    0x1080004008801020, 0x0840092002c03000, 0x1900200010400900, 0x0880100008000480,
+    0x4200100420080200, 0x8100020100080400, 0x0200040110886200, 0x0200008040220411,
+    0x0404800084400220, 0x0000401000402000, 0x0086001081220440, 0x0408800800100280,
+    0x000a001201040820, 0x8848800200840080, 0x4001000100040200, 0x0442000102105084,
+    0x9080010020804100, 0x0040404000201009, 0x0000808010002009, 0x2200090021d00100,
+    0x0008008008040080, 0x0004004002010040, 0x0011040008015042, 0x00000a0001768104,
+    0x0000800080204009, 0x2010004140002001, 0x9800200280100080, 0x1000100080080080,
+    0x0442000a00049020, 0x2100040080020080, 0x0800120400900148, 0x0010040a00128541,
+    0x2800804000800030, 0x1010002000400041, 0x4000200011004100, 0x0610008410800800,
+    0x0400802402800800, 0xc100020080800400, 0x0002000802000401, 0x0182085882000401,
+    0x0220204000808000, 0x2860100040024022, 0x0001002004110040, 0x99101042000a0020,
+    0x0004080004008080, 0x0010040002008080, 0x2012004881020004, 0x8300842444820011,
+    0x0088403882010200, 0x0820400080210100, 0x0110910040a00300, 0x0801100280080480,
+    0x0242009008200600, 0x1002000489500200, 0x0040800200010080, 0x0091800041000080,
+    0x0000209300488001, 0x04c1002414824001, 0x020020000b001041, 0x7000100004200901,
+    0x8002002004100802, 0x30010002084c0007, 0x0888221800813004, 0x4000002840840112,
+];
+
+const BISHOP_MAGICS: [u64; 64] = [
+    0xa010041108003100, 0x006082020a002900, 0x6810010619200000, 0x08281a0520000408,
+    0x0001104001000400, 0x0018901008048400, 0x00040a0210245280, 0x000200210808a402,
+    0x9140048410821200, 0x0800091010820041, 0x20504804832202c0, 0x0100091401081000,
+    0x8021011140000012, 0x0810020804450400, 0x208b0542109008a2, 0x0080084a08040204,
+    0x0040e2a80811244c, 0x2505022008008108, 0x0430220100420040, 0x010a040420220040,
+    0x1105000290400000, 0x0093001200822120, 0x4000a62048043004, 0x280120048a015004,
+    0x006090002a020814, 0x44042000240800d0, 0x01102800040a4400, 0x1004080080220040,
+    0x0001001011004024, 0x0010044000805040, 0x0914041200820100, 0x0004821012821480,
+    0x0024040500c05021, 0x0088611002080200, 0x0116080a00040020, 0x4000020080080080,
+    0x2450450140840040, 0x0000880201484100, 0x0222020404020092, 0x8081110600002e00,
+    0x2842101105000801, 0x1100809008001025, 0x00020202221c0400, 0x0422014022009020,
+    0x0210046102100c00, 0xc004008082029102, 0x00aa461801101200, 0x0404080080201108,
+    0x020542108c205002, 0x0410544804100100, 0x0040910841100000, 0x0400200042021100,
+    0x00004204850400c0, 0x0200100410a42102, 0x1040020801210102, 0x0805040410420000,
+    0x2884804130100200, 0x800c262201242000, 0x1058000194108800, 0x0014221054420204,
+    0x0104000012a02200, 0x0200881003300100, 0x0140400202840100, 0x0402020801010201,
+];
+
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SliderBackend {
+    Magic,
+    Pext,
+}
+
+struct SliderTable {
+    masks: [u64; 64],
+    magics: [u64; 64],
+    shifts: [u32; 64],
+    offsets: [usize; 64],
+    magic_attacks: Vec<u64>,
+    pext_attacks: Vec<u64>,
+}
+
+struct Tables {
+    rook: SliderTable,
+    bishop: SliderTable,
+    backend: SliderBackend,
+}
+
+static TABLES: OnceLock<Tables> = OnceLock::new();
+
+/// Walks every ray from `square` until it leaves the board or hits `occupancy`.
+/// The blocking square is included. With `edges == false` the last square
+/// of each ray is dropped, which gives the relevant occupancy mask.
+fn ray_attacks_slow(square: u8, occupancy: u64, directions: &[(i8, i8); 4], edges: bool) -> u64 {
+    let row = (square / 8) as i8;
+    let col = (square % 8) as i8;
+    let mut res: u64 = 0;
+
+    for &(dr, dc) in directions {
+        let mut r = row + dr;
+        let mut c = col + dc;
+        while (0..8).contains(&r) && (0..8).contains(&c) {
+            let is_edge = !((0..8).contains(&(r + dr)) && (0..8).contains(&(c + dc)));
+            if is_edge && !edges {
+                break;
+            }
+            let index = r * 8 + c;
+            res |= 1 << index;
+            if occupancy & (1 << index) != 0 {
+                break;
+            }
+            r += dr;
+            c += dc;
+        }
+    }
+
+    res
+}
+
+/// Portable parallel bit extract, used to lay out the PEXT tables.
+fn pext_soft(value: u64, mask: u64) -> u64 {
+    let mut res: u64 = 0;
+    let mut bit: u64 = 1;
+    let mut mask = mask;
+    while mask != 0 {
+        let lowest = mask & mask.wrapping_neg();
+        if value & lowest != 0 {
+            res |= bit;
+        }
+        bit <<= 1;
+        mask &= mask - 1;
+    }
+    res
+}
+
+impl SliderTable {
+    fn build(magics: [u64; 64], directions: &[(i8, i8); 4]) -> Self {
+        let mut masks = [0u64; 64];
+        let mut shifts = [0u32; 64];
+        let mut offsets = [0usize; 64];
+        let mut total = 0usize;
+
+        for square in 0..64 {
+            masks[square] = ray_attacks_slow(square as u8, 0, directions, false);
+            shifts[square] = 64 - masks[square].count_ones();
+            offsets[square] = total;
+            total += 1 << masks[square].count_ones();
+        }
+
+        let mut magic_attacks = vec![0u64; total];
+        let mut pext_attacks = vec![0u64; total];
+        let mut filled = vec![false; total];
+
+        for square in 0..64 {
+            let mask = masks[square];
+            let mut subset: u64 = 0;
+            loop {
+                let attacks = ray_attacks_slow(square as u8, subset, directions, true);
+
+                let index = offsets[square]
+                    + (subset.wrapping_mul(magics[square]) >> shifts[square]) as usize;
+                assert!(
+                    !filled[index] || magic_attacks[index] == attacks,
+                    "bad magic for square {}", square
+                );
+                filled[index] = true;
+                magic_attacks[index] = attacks;
+
+                pext_attacks[offsets[square] + pext_soft(subset, mask) as usize] = attacks;
+
+                subset = subset.wrapping_sub(mask) & mask;
+                if subset == 0 { break; }
+            }
+        }
+
+        Self { masks, magics, shifts, offsets, magic_attacks, pext_attacks }
+    }
+
+    #[inline]
+    fn attacks_magic(&self, square: u8, occupancy: u64) -> u64 {
+        let sq = square as usize;
+        let index = ((occupancy & self.masks[sq]).wrapping_mul(self.magics[sq]) >> self.shifts[sq]) as usize;
+        self.magic_attacks[self.offsets[sq] + index]
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "bmi2")]
+    #[inline]
+    fn attacks_pext(&self, square: u8, occupancy: u64) -> u64 {
+        let sq = square as usize;
+        let index = std::arch::x86_64::_pext_u64(occupancy, self.masks[sq]) as usize;
+        self.pext_attacks[self.offsets[sq] + index]
+    }
+}
+
+fn detect_backend() -> SliderBackend {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if std::is_x86_feature_detected!("bmi2") {
+            return SliderBackend::Pext;
+        }
+    }
+    SliderBackend::Magic
+}
+
+fn tables() -> &'static Tables {
+    TABLES.get_or_init(|| Tables {
+        rook: SliderTable::build(ROOK_MAGICS, &ROOK_DIRECTIONS),
+        bishop: SliderTable::build(BISHOP_MAGICS, &BISHOP_DIRECTIONS),
+        backend: detect_backend(),
+    })
+}
+
+#[inline]
+pub fn queen_attacks_magic(square: u8, occupancy: u64) -> u64 {
+    let tables = tables();
+    tables.rook.attacks_magic(square, occupancy) | tables.bishop.attacks_magic(square, occupancy)
+}
+
+/// Queen attacks using the PEXT tables, or `None` if the CPU lacks BMI2.
+#[inline]
+pub fn queen_attacks_pext(square: u8, occupancy: u64) -> Option<u64> {
+    #[cfg(target_arch = "x86_64")]
+    {
+        let tables = tables();
+        if tables.backend == SliderBackend::Pext {
+            // SAFETY: the backend is only `Pext` when BMI2 was detected at runtime.
+            return Some(unsafe {
+                tables.rook.attacks_pext(square, occupancy) | tables.bishop.attacks_pext(square, occupancy)
+            });
+        }
+    }
+    let _ = (square, occupancy);
+    None
+}
+
+/// Squares a queen on `square` attacks, stopping at (and including) the
+/// first occupied square of every ray.
+#[inline]
+pub fn queen_attacks(square: u8, occupancy: u64) -> u64 {
+    queen_attacks_pext(square, occupancy).unwrap_or_else(|| queen_attacks_magic(square, occupancy))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    fn queen_attacks_slow(square: u8, occupancy: u64) -> u64 {
+        ray_attacks_slow(square, occupancy, &ROOK_DIRECTIONS, true)
+            | ray_attacks_slow(square, occupancy, &BISHOP_DIRECTIONS, true)
+    }
+
+    #[test]
+    fn test_pext_soft() {
+        assert_eq!(pext_soft(0b1011_0110, 0b1111_0000), 0b1011);
+        assert_eq!(pext_soft(0b1011_0110, 0b0101_0101), 0b0110);
+        assert_eq!(pext_soft(u64::MAX, 0), 0);
+    }
+
+    #[test]
+    fn test_slider_backend() {
+        assert_eq!(queen_attacks_pext(0, 0).is_some(), tables().backend == SliderBackend::Pext);
+    }
+
+    #[test]
+    fn test_queen_attacks_random() {
+        let mut rng = rand::thread_rng();
+        let tables = tables();
+        for _ in 0..20000 {
+            let square: u8 = rng.gen_range(0..64);
+            let occupancy: u64 = rng.r#gen::<u64>() & rng.r#gen::<u64>();
+            let expected = queen_attacks_slow(square, occupancy);
+            assert_eq!(queen_attacks_magic(square, occupancy), expected);
+            assert_eq!(queen_attacks(square, occupancy), expected);
+            if let Some(attacks) = queen_attacks_pext(square, occupancy) {
+                assert_eq!(attacks, expected);
+            }
+            let sq = square as usize;
+            let soft = tables.rook.pext_attacks[tables.rook.offsets[sq] + pext_soft(occupancy, tables.rook.masks[sq]) as usize]
+                | tables.bishop.pext_attacks[tables.bishop.offsets[sq] + pext_soft(occupancy, tables.bishop.masks[sq]) as usize];
+            assert_eq!(soft, expected);
+        }
+    }
+}
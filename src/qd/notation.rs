@@ -0,0 +1,285 @@
+use std::fmt;
+use std::str::FromStr;
+use crate::qd::state::GameState;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotationError {
+    WrongFieldCount(usize),
+    WrongRankCount(usize),
+    BadRankLength { rank: u8, len: usize },
+    UnexpectedChar(char),
+    MissingQueen { white: bool },
+    DuplicateQueen { white: bool },
+    BadSideToMove(String),
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotationError::WrongFieldCount(n) =>
+                write!(f, "expected 2 space-separated fields, found {}", n),
+            NotationError::WrongRankCount(n) =>
+                write!(f, "expected 8 ranks separated by '/', found {}", n),
+            NotationError::BadRankLength { rank, len } =>
+                write!(f, "rank {} describes {} squares instead of 8", rank, len),
+            NotationError::UnexpectedChar(ch) =>
+                write!(f, "unexpected character '{}'", ch),
+            NotationError::MissingQueen { white } =>
+                write!(f, "missing {} queen", if *white { "white" } else { "black" }),
+            NotationError::DuplicateQueen { white } =>
+                write!(f, "more than one {} queen", if *white { "white" } else { "black" }),
+            NotationError::BadSideToMove(s) =>
+                write!(f, "side to move must be 'w' or 'b', found \"{}\"", s),
+        }
+    }
+}
+
+impl std::error::Error for NotationError {}
+
+fn place_queen(slot: &mut Option<u8>, index: u8, white: bool) -> Result<(), NotationError> {
+    if slot.is_some() {
+        return Err(NotationError::DuplicateQueen { white });
+    }
+    *slot = Some(index);
+    Ok(())
+}
+
+/// Algebraic name of a square, `a1`..`h8`.
+pub fn square_name(square: u8) -> String {
+    let file = (b'a' + square % 8) as char;
+    let rank = (b'1' + square / 8) as char;
+    format!("{}{}", file, rank)
+}
+
+pub fn parse_square(name: &str) -> Option<u8> {
+    let bytes = name.as_bytes();
+    if bytes.len() != 2 {
+        return None;
+    }
+    let (file, rank) = (bytes[0], bytes[1]);
+    if !(b'a'..=b'h').contains(&file) || !(b'1'..=b'8').contains(&rank) {
+        return None;
+    }
+    Some((rank - b'1') * 8 + (file - b'a'))
+}
+
+impl GameState {
+    /// Position notation, modelled on FEN: the ranks from 8 down to 1 separated
+    /// by `/`, then the side to move.
+    ///
+    /// Inside a rank, `x` is a block, `W` and `B` are the queens, `Q` is both
+    /// queens on one square (right after a capture) and a digit `1`..`8` is a
+    /// run of empty squares. The side to move is `w` or `b`.
+    ///
+    /// The default start position is `3B4/8/8/8/8/8/8/4W3 w`.
+    pub fn from_notation(notation: &str) -> Result<Self, NotationError> {
+        let fields: Vec<&str> = notation.split_whitespace().collect();
+        if fields.len() != 2 {
+            return Err(NotationError::WrongFieldCount(fields.len()));
+        }
+
+        let ranks: Vec<&str> = fields[0].split('/').collect();
+        if ranks.len() != 8 {
+            return Err(NotationError::WrongRankCount(ranks.len()));
+        }
+
+        let mut wqueen: Option<u8> = None;
+        let mut bqueen: Option<u8> = None;
+        let mut blocks: u64 = 0;
+
+        for (i, rank_str) in ranks.iter().enumerate() {
+            let rank = 7 - i as u8;
+            let mut file: usize = 0;
+            for ch in rank_str.chars() {
+                if let Some(run) = ch.to_digit(10) {
+                    if !(1..=8).contains(&run) {
+                        return Err(NotationError::UnexpectedChar(ch));
+                    }
+                    file += run as usize;
+                    continue;
+                }
+                if file >= 8 {
+                    return Err(NotationError::BadRankLength { rank: rank + 1, len: file + 1 });
+                }
+                let index = rank * 8 + file as u8;
+                match ch {
+                    'x' => blocks |= 1 << index,
+                    'W' => place_queen(&mut wqueen, index, true)?,
+                    'B' => place_queen(&mut bqueen, index, false)?,
+                    'Q' => {
+                        place_queen(&mut wqueen, index, true)?;
+                        place_queen(&mut bqueen, index, false)?;
+                    }
+                    _ => return Err(NotationError::UnexpectedChar(ch)),
+                }
+                file += 1;
+            }
+            if file != 8 {
+                return Err(NotationError::BadRankLength { rank: rank + 1, len: file });
+            }
+        }
+
+        let wqueen = wqueen.ok_or(NotationError::MissingQueen { white: true })?;
+        let bqueen = bqueen.ok_or(NotationError::MissingQueen { white: false })?;
+        let is_white_turn = match fields[1] {
+            "w" => true,
+            "b" => false,
+            other => return Err(NotationError::BadSideToMove(other.to_string())),
+        };
+
+        Ok(GameState::new(Some(wqueen), Some(bqueen), Some(blocks), Some(is_white_turn)))
+    }
+
+    pub fn to_notation(self) -> String {
+        let mut res = String::new();
+        for rank in (0..8u8).rev() {
+            let mut empty = 0;
+            for file in 0..8u8 {
+                let index = rank * 8 + file;
+                let ch = if self.wqueen() == index && self.bqueen() == index {
+                    Some('Q')
+                } else if self.wqueen() == index {
+                    Some('W')
+                } else if self.bqueen() == index {
+                    Some('B')
+                } else if self.blocks() & (1 << index) != 0 {
+                    Some('x')
+                } else {
+                    None
+                };
+                match ch {
+                    Some(ch) => {
+                        if empty > 0 {
+                            res.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        res.push(ch);
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                res.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                res.push('/');
+            }
+        }
+        res.push(' ');
+        res.push(if self.is_white_turn() { 'w' } else { 'b' });
+        res
+    }
+}
+
+impl fmt::Display for GameState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_notation())
+    }
+}
+
+impl FromStr for GameState {
+    type Err = NotationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        GameState::from_notation(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qd::utils::*;
+    use rand::Rng;
+
+    #[test]
+    fn test_square_names() {
+        assert_eq!(square_name(0), "a1");
+        assert_eq!(square_name(4), "e1");
+        assert_eq!(square_name(59), "d8");
+        assert_eq!(square_name(63), "h8");
+        for square in 0..64 {
+            assert_eq!(parse_square(&square_name(square)), Some(square));
+        }
+        assert_eq!(parse_square("i1"), None);
+        assert_eq!(parse_square("a9"), None);
+        assert_eq!(parse_square("a10"), None);
+        assert_eq!(parse_square(""), None);
+    }
+
+    #[test]
+    fn test_default_notation() {
+        assert_eq!(GameState::def().to_notation(), "3B4/8/8/8/8/8/8/4W3 w");
+        assert_eq!(GameState::from_notation("3B4/8/8/8/8/8/8/4W3 w"), Ok(GameState::def()));
+    }
+
+    #[test]
+    fn test_notation_matches_visual() {
+        let state = vgs("
+            ...#....
+            .B......
+            ........
+            ..#.....
+            ........
+            ........
+            ....W...
+            ....#...
+        ", false);
+        assert_eq!(state.to_notation(), "3x4/1B6/8/2x5/8/8/4W3/4x3 b");
+        assert_eq!("3x4/1B6/8/2x5/8/8/4W3/4x3 b".parse::<GameState>(), Ok(state));
+    }
+
+    #[test]
+    fn test_notation_round_trip() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            let wqueen: u8 = rng.gen_range(0..64);
+            let bqueen: u8 = rng.gen_range(0..64);
+            let blocks: u64 = rng.r#gen::<u64>() & !(1u64 << wqueen) & !(1u64 << bqueen);
+            let state = GameState::new(Some(wqueen), Some(bqueen), Some(blocks), Some(rng.r#gen()));
+            assert_eq!(GameState::from_notation(&state.to_notation()), Ok(state));
+        }
+    }
+
+    #[test]
+    fn test_notation_errors() {
+        assert_eq!(
+            GameState::from_notation("3B4/8/8/8/8/8/8/4W3"),
+            Err(NotationError::WrongFieldCount(1))
+        );
+        assert_eq!(
+            GameState::from_notation("3B4/8/8/8/8/8/4W3 w"),
+            Err(NotationError::WrongRankCount(7))
+        );
+        assert_eq!(
+            GameState::from_notation("3B4/8/8/8/8/8/8/4W4 w"),
+            Err(NotationError::BadRankLength { rank: 1, len: 9 })
+        );
+        assert_eq!(
+            GameState::from_notation("3B4/8/8/8/8/8/8/4W2 w"),
+            Err(NotationError::BadRankLength { rank: 1, len: 7 })
+        );
+        assert_eq!(
+            GameState::from_notation("3B4/8/8/8/8/8/8/4W2k w"),
+            Err(NotationError::UnexpectedChar('k'))
+        );
+        assert_eq!(
+            GameState::from_notation("3B4/8/8/8/8/8/8/8 w"),
+            Err(NotationError::MissingQueen { white: true })
+        );
+        assert_eq!(
+            GameState::from_notation("3B4/8/8/8/8/8/8/3WB3 w"),
+            Err(NotationError::DuplicateQueen { white: false })
+        );
+        assert_eq!(
+            GameState::from_notation("3B4/8/8/8/8/8/8/4W3 white"),
+            Err(NotationError::BadSideToMove("white".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_notation_captured() {
+        let state = GameState::new(Some(12), Some(12), Some(1 << 4), Some(true));
+        assert_eq!(state.to_notation(), "8/8/8/8/8/8/4Q3/4x3 w");
+        assert_eq!(GameState::from_notation(&state.to_notation()), Ok(state));
+    }
+}
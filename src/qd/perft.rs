@@ -0,0 +1,129 @@
+use crate::qd::legalcomp::get_possible_legal_moves;
+use crate::qd::state::GameState;
+
+fn legal_moves_if_running(state: &GameState) -> u64 {
+    if state.wqueen() == state.bqueen() {
+        return 0;
+    }
+    get_possible_legal_moves(state)
+}
+
+/// Number of move sequences of exactly `depth` plies from `state`.
+/// Games that end earlier contribute nothing.
+pub fn perft(state: &GameState, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let legal_moves = legal_moves_if_running(state);
+    if depth == 1 {
+        return legal_moves.count_ones() as u64;
+    }
+
+    let mut nodes = 0;
+    let mut rest = legal_moves;
+    while rest != 0 {
+        let to = rest.trailing_zeros() as u8;
+        rest &= rest - 1;
+        let mut child = *state;
+        child.make_move(to);
+        nodes += perft(&child, depth - 1);
+    }
+    nodes
+}
+
+/// Perft split by the first move, in square order.
+pub fn perft_divide(state: &GameState, depth: u32) -> Vec<(u8, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
+    let mut res = Vec::new();
+    let mut rest = legal_moves_if_running(state);
+    while rest != 0 {
+        let to = rest.trailing_zeros() as u8;
+        rest &= rest - 1;
+        let mut child = *state;
+        child.make_move(to);
+        res.push((to, perft(&child, depth - 1)));
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qd::legalcomp::get_possible_legal_moves_info_slow;
+
+    // Reference counts. `test_perft_slow` recomputes them with the
+    // ray-walking move generator.
+    const REFERENCE: &[(&str, &[u64])] = &[
+        ("3B4/8/8/8/8/8/8/4W3 w", &[1, 21, 434, 8457, 156892, 2795302]),
+        ("3Bx3/4x3/8/1x4x1/1x5x/7x/8/4W3 w", &[1, 16, 207, 3346, 51377]),
+        ("3B3x/5x2/2x2x2/7x/3x4/8/1x6/4W3 w", &[1, 21, 271, 3784, 43219]),
+        ("3B4/4x3/4x3/8/2xx4/2x5/8/4W2x w", &[1, 14, 182, 2648, 32788]),
+        ("3B4/1x3x2/1xx5/8/8/1x6/7x/4W3 w", &[1, 21, 392, 6267, 85281]),
+        ("3x3x/8/5x2/4B3/3W4/2x5/8/x3x3 b", &[1, 20, 349, 5024, 69796]),
+        ("7B/xxxxxxx1/8/8/8/8/1xxxxxxx/W7 w", &[1, 12, 144, 1296, 11432]),
+    ];
+
+    #[test]
+    fn test_perft_reference() {
+        for (notation, counts) in REFERENCE {
+            let state = GameState::from_notation(notation).unwrap();
+            for (depth, &expected) in counts.iter().enumerate() {
+                assert_eq!(perft(&state, depth as u32), expected, "{} depth {}", notation, depth);
+            }
+        }
+    }
+
+    fn perft_slow(state: &GameState, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        if state.wqueen() == state.bqueen() {
+            return 0;
+        }
+        let mut rest = if state.is_white_turn() {
+            get_possible_legal_moves_info_slow(state.wqueen(), state.bqueen(), state.blocks())
+        } else {
+            get_possible_legal_moves_info_slow(state.bqueen(), state.wqueen(), state.blocks())
+        };
+        let mut nodes = 0;
+        while rest != 0 {
+            let to = rest.trailing_zeros() as u8;
+            rest &= rest - 1;
+            let mut child = *state;
+            child.make_move(to);
+            nodes += perft_slow(&child, depth - 1);
+        }
+        nodes
+    }
+
+    #[test]
+    fn test_perft_slow() {
+        for (notation, counts) in REFERENCE {
+            let state = GameState::from_notation(notation).unwrap();
+            for (depth, &expected) in counts.iter().enumerate().take(5) {
+                assert_eq!(perft_slow(&state, depth as u32), expected, "{} depth {}", notation, depth);
+            }
+        }
+    }
+
+    #[test]
+    fn test_perft_divide_sums() {
+        for (notation, counts) in REFERENCE {
+            let state = GameState::from_notation(notation).unwrap();
+            let depth = counts.len() as u32 - 1;
+            let divide = perft_divide(&state, depth);
+            assert_eq!(divide.len() as u64, counts[1]);
+            assert_eq!(divide.iter().map(|(_, n)| n).sum::<u64>(), counts[depth as usize]);
+        }
+    }
+
+    #[test]
+    fn test_perft_game_over() {
+        let state = GameState::new(Some(12), Some(12), None, Some(false));
+        assert_eq!(perft(&state, 0), 1);
+        assert_eq!(perft(&state, 1), 0);
+        assert_eq!(perft(&state, 3), 0);
+    }
+}
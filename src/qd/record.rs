@@ -0,0 +1,562 @@
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::qd::error::QdError;
+use crate::qd::game::Game;
+use crate::qd::notation::{parse_square, square_name};
+use crate::qd::state::GameState;
+
+/// How a finished game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    Capture,
+    NoMoves,
+    Unfinished,
+}
+
+impl Termination {
+    pub fn of(state: &GameState) -> Self {
+        if state.wqueen() == state.bqueen() {
+            Termination::Capture
+        } else if state.result().is_some() {
+            Termination::NoMoves
+        } else {
+            Termination::Unfinished
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Termination::Capture => "capture",
+            Termination::NoMoves => "no moves",
+            Termination::Unfinished => "unfinished",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "capture" => Some(Termination::Capture),
+            "no moves" => Some(Termination::NoMoves),
+            "unfinished" => Some(Termination::Unfinished),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordError {
+    Io(String),
+    BadHeader { line: usize, text: String },
+    BadHeaderValue { key: String, value: String },
+    BadStart(QdError),
+    BadSquare { ply: usize, text: String },
+    IllegalMove { ply: usize, square: u8 },
+    MoveAfterEnd { ply: usize },
+    ResultMismatch { header: String, actual: String },
+    TerminationMismatch { header: String, actual: String },
+    NoGames,
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordError::Io(err) => write!(f, "{}", err),
+            RecordError::BadHeader { line, text } =>
+                write!(f, "line {}: malformed header \"{}\"", line, text),
+            RecordError::BadHeaderValue { key, value } =>
+                write!(f, "invalid value \"{}\" for header {}", value, key),
+            RecordError::BadStart(err) => write!(f, "invalid start position: {}", err),
+            RecordError::BadSquare { ply, text } =>
+                write!(f, "ply {}: \"{}\" is not a square", ply, text),
+            RecordError::IllegalMove { ply, square } =>
+                write!(f, "ply {}: {} is not a legal move", ply, square_name(*square)),
+            RecordError::MoveAfterEnd { ply } =>
+                write!(f, "ply {}: the game is already over", ply),
+            RecordError::ResultMismatch { header, actual } =>
+                write!(f, "result header says {} but the moves give {}", header, actual),
+            RecordError::TerminationMismatch { header, actual } =>
+                write!(f, "termination header says {} but the moves give {}", header, actual),
+            RecordError::NoGames => write!(f, "no games found"),
+        }
+    }
+}
+
+impl std::error::Error for RecordError {}
+
+/// A recorded game, written in a PGN-like text format:
+///
+/// ```text
+/// [White "basic3"]
+/// [Black "random"]
+/// [Date "2025.01.31"]
+/// [Start "3B4/8/8/8/8/8/8/4W3 w"]
+/// [Result "1-0"]
+/// [Termination "capture"]
+///
+/// 1. e2 d7 2. e7 d6 3. d6 1-0
+/// ```
+///
+/// Backslashes, quotes and line breaks in header values are escaped with
+/// a backslash, as `\\`, `\"`, `\n` and `\r`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameRecord {
+    pub white: String,
+    pub black: String,
+    pub date: String,
+    pub start: GameState,
+    pub moves: Vec<u8>,
+    pub result: Option<bool>,
+    pub termination: Termination,
+    pub extra_headers: Vec<(String, String)>,
+}
+
+fn result_str(result: Option<bool>) -> &'static str {
+    match result {
+        Some(true) => "1-0",
+        Some(false) => "0-1",
+        None => "*",
+    }
+}
+
+fn parse_result(s: &str) -> Option<Option<bool>> {
+    match s {
+        "1-0" => Some(Some(true)),
+        "0-1" => Some(Some(false)),
+        "*" => Some(None),
+        _ => None,
+    }
+}
+
+/// Today's date in UTC, formatted as `YYYY.MM.DD`.
+pub fn today() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    // Civil-from-days conversion from Howard Hinnant's date algorithms.
+    let z = (secs / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+/// Escapes a header value so it stays on one line inside its quotes.
+fn escape_value(value: &str) -> String {
+    let mut res = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => res.push_str("\\\\"),
+            '"' => res.push_str("\\\""),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            _ => res.push(c),
+        }
+    }
+    res
+}
+
+fn unescape_value(text: &str) -> Option<String> {
+    let mut res = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => res.push(match chars.next()? {
+                '\\' => '\\',
+                '"' => '"',
+                'n' => '\n',
+                'r' => '\r',
+                _ => return None,
+            }),
+            '"' => return None,
+            _ => res.push(c),
+        }
+    }
+    Some(res)
+}
+
+fn parse_header(line: &str) -> Option<(String, String)> {
+    let inner = line.strip_prefix('[')?.strip_suffix(']')?;
+    let (key, value) = inner.split_once(' ')?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+    if key.is_empty() {
+        return None;
+    }
+    Some((key.to_string(), unescape_value(value)?))
+}
+
+fn is_move_number(token: &str) -> bool {
+    let digits = token.trim_end_matches('.');
+    digits.len() < token.len() && !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+impl GameRecord {
+    pub fn from_game(game: &Game, white: &str, black: &str) -> Self {
+        let end = game.state();
+        Self {
+            white: white.to_string(),
+            black: black.to_string(),
+            date: today(),
+            start: game.start(),
+            moves: game.moves().iter().map(|mv| mv.to).collect(),
+            result: end.result(),
+            termination: Termination::of(&end),
+            extra_headers: Vec::new(),
+        }
+    }
+
+    /// Replays the moves from the start position, checking each one.
+    pub fn to_game(&self) -> Result<Game, RecordError> {
+        let mut game = Game::new(self.start);
+        for (i, &square) in self.moves.iter().enumerate() {
+            game.try_make_move(square).map_err(|err| match err {
+                QdError::GameOver => RecordError::MoveAfterEnd { ply: i + 1 },
+                _ => RecordError::IllegalMove { ply: i + 1, square },
+            })?;
+        }
+        Ok(game)
+    }
+
+    pub fn header(&self, key: &str) -> Option<&str> {
+        self.extra_headers
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn set_header(&mut self, key: &str, value: &str) {
+        match self.extra_headers.iter_mut().find(|(k, _)| k == key) {
+            Some(entry) => entry.1 = value.to_string(),
+            None => self.extra_headers.push((key.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut res = String::new();
+        let mut headers = vec![
+            ("White".to_string(), self.white.clone()),
+            ("Black".to_string(), self.black.clone()),
+            ("Date".to_string(), self.date.clone()),
+            ("Start".to_string(), self.start.to_notation()),
+            ("Result".to_string(), result_str(self.result).to_string()),
+            ("Termination".to_string(), self.termination.as_str().to_string()),
+        ];
+        headers.extend(self.extra_headers.iter().cloned());
+        for (key, value) in headers {
+            res.push_str(&format!("[{} \"{}\"]\n", key, escape_value(&value)));
+        }
+        res.push('\n');
+
+        let mut tokens = Vec::new();
+        let mut is_white_turn = self.start.is_white_turn();
+        let mut number = 1;
+        for (i, &square) in self.moves.iter().enumerate() {
+            if is_white_turn {
+                tokens.push(format!("{}.", number));
+            } else if i == 0 {
+                tokens.push(format!("{}...", number));
+            }
+            tokens.push(square_name(square));
+            if !is_white_turn {
+                number += 1;
+            }
+            is_white_turn = !is_white_turn;
+        }
+        tokens.push(result_str(self.result).to_string());
+
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > 80 {
+                res.push_str(&line);
+                res.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        res.push_str(&line);
+        res.push('\n');
+        res
+    }
+
+    fn from_parts(headers: Vec<(String, String)>, movetext: &str) -> Result<Self, RecordError> {
+        let mut record = GameRecord {
+            white: "?".to_string(),
+            black: "?".to_string(),
+            date: "????.??.??".to_string(),
+            start: GameState::def(),
+            moves: Vec::new(),
+            result: None,
+            termination: Termination::Unfinished,
+            extra_headers: Vec::new(),
+        };
+        let mut result_header = None;
+        let mut termination_header = None;
+
+        for (key, value) in headers {
+            match key.as_str() {
+                "White" => record.white = value,
+                "Black" => record.black = value,
+                "Date" => record.date = value,
+                "Start" => {
+                    let start = GameState::from_notation(&value)
+                        .map_err(|err| RecordError::BadStart(err.into()))?;
+                    start.validate().map_err(RecordError::BadStart)?;
+                    record.start = start;
+                }
+                "Result" => result_header = Some(value),
+                "Termination" => termination_header = Some(value),
+                _ => record.extra_headers.push((key, value)),
+            }
+        }
+
+        let mut ply = 0;
+        for token in movetext.split_whitespace() {
+            if is_move_number(token) || parse_result(token).is_some() {
+                continue;
+            }
+            ply += 1;
+            let square = parse_square(token).ok_or(RecordError::BadSquare {
+                ply,
+                text: token.to_string(),
+            })?;
+            record.moves.push(square);
+        }
+
+        let end = record.to_game()?.state();
+        record.result = end.result();
+        record.termination = Termination::of(&end);
+
+        if let Some(value) = result_header {
+            let header = parse_result(&value).ok_or(RecordError::BadHeaderValue {
+                key: "Result".to_string(),
+                value: value.clone(),
+            })?;
+            if header != record.result {
+                return Err(RecordError::ResultMismatch {
+                    header: value,
+                    actual: result_str(record.result).to_string(),
+                });
+            }
+        }
+        if let Some(value) = termination_header {
+            let header = Termination::parse(&value).ok_or(RecordError::BadHeaderValue {
+                key: "Termination".to_string(),
+                value: value.clone(),
+            })?;
+            if header != record.termination {
+                return Err(RecordError::TerminationMismatch {
+                    header: value,
+                    actual: record.termination.as_str().to_string(),
+                });
+            }
+        }
+
+        Ok(record)
+    }
+
+    /// Parses every game in `text`. Games follow each other, each one
+    /// starting with its header block.
+    pub fn parse_all(text: &str) -> Result<Vec<Self>, RecordError> {
+        let mut records = Vec::new();
+        let mut headers = Vec::new();
+        let mut movetext = String::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with('[') {
+                if !movetext.is_empty() {
+                    records.push(Self::from_parts(std::mem::take(&mut headers), &movetext)?);
+                    movetext.clear();
+                }
+                let header = parse_header(line).ok_or(RecordError::BadHeader {
+                    line: i + 1,
+                    text: line.to_string(),
+                })?;
+                headers.push(header);
+            } else {
+                movetext.push_str(line);
+                movetext.push(' ');
+            }
+        }
+        if !headers.is_empty() || !movetext.is_empty() {
+            records.push(Self::from_parts(headers, &movetext)?);
+        }
+
+        Ok(records)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, RecordError> {
+        Self::parse_all(text)?.into_iter().next().ok_or(RecordError::NoGames)
+    }
+}
+
+impl fmt::Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_text())
+    }
+}
+
+pub fn load_records(path: &Path) -> Result<Vec<GameRecord>, RecordError> {
+    let text = fs::read_to_string(path).map_err(|e| RecordError::Io(e.to_string()))?;
+    let records = GameRecord::parse_all(&text)?;
+    if records.is_empty() {
+        return Err(RecordError::NoGames);
+    }
+    Ok(records)
+}
+
+/// Appends games to `path`, creating the file if needed.
+pub fn append_records(path: &Path, records: &[GameRecord]) -> std::io::Result<()> {
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+    for record in records {
+        writeln!(file, "{}", record.to_text())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qd::legalcomp::get_possible_legal_moves;
+    use rand::Rng;
+
+    fn random_game(start: GameState) -> Game {
+        let mut rng = rand::thread_rng();
+        let mut game = Game::new(start);
+        while game.result().is_none() {
+            let legal_moves = get_possible_legal_moves(&game.state());
+            let squares: Vec<u8> = (0..64).filter(|i| legal_moves & (1 << i) != 0).collect();
+            game.make_move(squares[rng.gen_range(0..squares.len())]);
+        }
+        game
+    }
+
+    #[test]
+    fn test_write_record() {
+        let mut game = Game::new(GameState::def());
+        for square in [12, 51, 52, 43, 43] {
+            game.make_move(square);
+        }
+        let mut record = GameRecord::from_game(&game, "basic3", "random");
+        record.date = "2025.01.31".to_string();
+        assert_eq!(record.result, Some(true));
+        assert_eq!(record.termination, Termination::Capture);
+        assert_eq!(record.to_text(), "\
+[White \"basic3\"]
+[Black \"random\"]
+[Date \"2025.01.31\"]
+[Start \"3B4/8/8/8/8/8/8/4W3 w\"]
+[Result \"1-0\"]
+[Termination \"capture\"]
+
+1. e2 d7 2. e7 d6 3. d6 1-0
+");
+        assert_eq!(GameRecord::parse(&record.to_text()), Ok(record));
+    }
+
+    #[test]
+    fn test_black_to_move_start() {
+        let start = GameState::new(None, None, None, Some(false));
+        let mut game = Game::new(start);
+        game.make_move(51);
+        game.make_move(12);
+        let record = GameRecord::from_game(&game, "a", "b");
+        assert!(record.to_text().ends_with("1... d7 2. e2 *\n"));
+        assert_eq!(GameRecord::parse(&record.to_text()), Ok(record));
+    }
+
+    #[test]
+    fn test_round_trip_many() {
+        let mut text = String::new();
+        let mut records = Vec::new();
+        for i in 0..50 {
+            let game = random_game(GameState::def_rand());
+            let mut record = GameRecord::from_game(&game, "random", "random");
+            record.set_header("Round", &i.to_string());
+            text.push_str(&record.to_text());
+            text.push('\n');
+            records.push(record);
+        }
+        let parsed = GameRecord::parse_all(&text).unwrap();
+        assert_eq!(parsed, records);
+        assert_eq!(parsed[7].header("Round"), Some("7"));
+        for record in parsed {
+            assert_eq!(record.to_game().unwrap().state().result(), record.result);
+        }
+    }
+
+    #[test]
+    fn test_escaped_headers() {
+        let mut record = GameRecord::from_game(&Game::new(GameState::def()), "say \"hi\"", "a\\b");
+        record.set_header("Comment", "two\nlines");
+        let text = record.to_text();
+        assert!(text.contains("[White \"say \\\"hi\\\"\"]\n"));
+        assert!(text.contains("[Comment \"two\\nlines\"]\n"));
+        assert_eq!(GameRecord::parse(&text), Ok(record));
+        assert_eq!(
+            GameRecord::parse("[White \"a\\qb\"]\n\n*"),
+            Err(RecordError::BadHeader { line: 1, text: "[White \"a\\qb\"]".to_string() })
+        );
+    }
+
+    #[test]
+    fn test_record_errors() {
+        assert_eq!(
+            GameRecord::parse("[Start \"3Q4/8/8/8/8/8/8/8 w\"]\n\n*"),
+            Err(RecordError::BadStart(QdError::QueensOverlap))
+        );
+        assert_eq!(GameRecord::parse(""), Err(RecordError::NoGames));
+        assert_eq!(
+            GameRecord::parse("[White basic3]\n\n*"),
+            Err(RecordError::BadHeader { line: 1, text: "[White basic3]".to_string() })
+        );
+        assert_eq!(
+            GameRecord::parse("1. e2 z9 *"),
+            Err(RecordError::BadSquare { ply: 2, text: "z9".to_string() })
+        );
+        assert_eq!(
+            GameRecord::parse("1. e2 e2 *"),
+            Err(RecordError::IllegalMove { ply: 2, square: 12 })
+        );
+        assert_eq!(
+            GameRecord::parse("[Result \"0-1\"]\n\n1. e2 d7 2. e7 d6 3. d6"),
+            Err(RecordError::ResultMismatch { header: "0-1".to_string(), actual: "1-0".to_string() })
+        );
+        assert_eq!(
+            GameRecord::parse("[Termination \"no moves\"]\n\n1. e2 d7 2. e7 d6 3. d6"),
+            Err(RecordError::TerminationMismatch {
+                header: "no moves".to_string(),
+                actual: "capture".to_string(),
+            })
+        );
+        assert_eq!(
+            GameRecord::parse("1. e2 d7 2. e7 d6 3. d6 4. a1"),
+            Err(RecordError::MoveAfterEnd { ply: 6 })
+        );
+        assert!(matches!(
+            GameRecord::parse("[Start \"8/8 w\"]\n\n*"),
+            Err(RecordError::BadStart(_))
+        ));
+    }
+
+    #[test]
+    fn test_today_format() {
+        let date = today();
+        assert_eq!(date.len(), 10);
+        assert_eq!(&date[4..5], ".");
+        assert_eq!(&date[7..8], ".");
+    }
+}
@@ -0,0 +1,262 @@
+use crate::qd::state::GameState;
+
+const NOT_A_FILE: u64 = 0xfefefefefefefefe;
+const NOT_H_FILE: u64 = 0x7f7f7f7f7f7f7f7f;
+
+/// Squares one king step away from any square of `bb`.
+///
+/// A queen slides through empty squares that are each a king step from the
+/// next, so the empty squares a queen can reach by queen moves are exactly
+/// those it can reach by king steps. The flood fills below use this.
+pub fn king_neighbours(bb: u64) -> u64 {
+    let sides = (bb << 1 & NOT_A_FILE) | (bb >> 1 & NOT_H_FILE);
+    let row = bb | sides;
+    (sides | row << 8 | row >> 8) & !bb
+}
+
+/// The squares of `passable` connected to `seed` by king steps, not
+/// counting `seed` itself unless it is passable.
+pub fn flood_fill(seed: u64, passable: u64) -> u64 {
+    let mut filled = seed & passable;
+    let mut frontier = king_neighbours(seed) & passable;
+    while frontier != 0 {
+        filled |= frontier;
+        frontier = king_neighbours(frontier) & passable & !filled;
+    }
+    filled
+}
+
+fn empty_squares(state: &GameState) -> u64 {
+    !state.blocks() & !(1u64 << state.wqueen()) & !(1u64 << state.bqueen())
+}
+
+/// Empty squares that `queen` can reach by a sequence of queen moves.
+pub fn reachable(state: &GameState, queen: u8) -> u64 {
+    flood_fill(1u64 << queen, empty_squares(state))
+}
+
+/// Whether the queens can still meet, that is whether one queen's region
+/// borders the other queen.
+pub fn queens_share_region(state: &GameState) -> bool {
+    if state.wqueen() == state.bqueen() {
+        return true;
+    }
+    let white = reachable(state, state.wqueen()) | 1u64 << state.wqueen();
+    king_neighbours(white) & 1u64 << state.bqueen() != 0
+}
+
+/// Squares whose blocking would split the component of `graph` containing
+/// `root` into more pieces. The root itself is never reported.
+pub fn articulation_squares(root: u8, graph: u64) -> u64 {
+    let graph = graph | 1u64 << root;
+    let mut disc = [0u8; 64];
+    let mut low = [0u8; 64];
+    let mut res = 0u64;
+    let mut time = 1u8;
+
+    // Iterative DFS: each frame is a square and its unvisited neighbours.
+    let mut stack: Vec<(u8, u64)> = Vec::new();
+    disc[root as usize] = time;
+    low[root as usize] = time;
+    stack.push((root, king_neighbours(1u64 << root) & graph));
+
+    while let Some(frame) = stack.last_mut() {
+        let (square, rest) = *frame;
+        if rest == 0 {
+            stack.pop();
+            if let Some(&(parent, _)) = stack.last() {
+                low[parent as usize] = low[parent as usize].min(low[square as usize]);
+                if parent != root && low[square as usize] >= disc[parent as usize] {
+                    res |= 1u64 << parent;
+                }
+            }
+            continue;
+        }
+        let next = rest.trailing_zeros() as u8;
+        frame.1 &= rest - 1;
+        if disc[next as usize] != 0 {
+            low[square as usize] = low[square as usize].min(disc[next as usize]);
+            continue;
+        }
+        time += 1;
+        disc[next as usize] = time;
+        low[next as usize] = time;
+        stack.push((next, king_neighbours(1u64 << next) & graph));
+    }
+
+    res
+}
+
+/// How the empty squares split up between the queens.
+#[derive(PartialEq)]
+#[derive(Debug, Eq)]
+#[derive(Copy, Clone)]
+pub struct Regions {
+    /// Empty squares the white queen can reach.
+    pub white: u64,
+    /// Empty squares the black queen can reach.
+    pub black: u64,
+    pub shared: bool,
+    /// Empty squares that would cut a queen's region in two if blocked.
+    pub articulation: u64,
+}
+
+impl Regions {
+    pub fn of(state: &GameState) -> Self {
+        let white = reachable(state, state.wqueen());
+        let black = reachable(state, state.bqueen());
+        let articulation = articulation_squares(state.wqueen(), white)
+            | articulation_squares(state.bqueen(), black);
+        Self {
+            white,
+            black,
+            shared: queens_share_region(state),
+            articulation: articulation & (white | black),
+        }
+    }
+
+    pub fn white_size(&self) -> u32 {
+        self.white.count_ones()
+    }
+
+    pub fn black_size(&self) -> u32 {
+        self.black.count_ones()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qd::magic::queen_attacks;
+    use crate::qd::utils::vgs;
+    use crate::rng::rng_from_seed;
+    use rand::Rng;
+
+    fn random_state(rng: &mut impl Rng) -> GameState {
+        let mut blocks = 0u64;
+        for _ in 0..rng.gen_range(0..40) {
+            blocks |= 1 << rng.gen_range(0..64);
+        }
+        let wqueen = rng.gen_range(0..64u8);
+        let bqueen = (wqueen + rng.gen_range(1..64u8)) % 64;
+        blocks &= !(1u64 << wqueen) & !(1u64 << bqueen);
+        GameState::new(Some(wqueen), Some(bqueen), Some(blocks), Some(rng.gen_bool(0.5)))
+    }
+
+    fn reachable_slow(state: &GameState, queen: u8) -> u64 {
+        let empty = empty_squares(state);
+        let mut seen = 0u64;
+        let mut todo = vec![queen];
+        while let Some(square) = todo.pop() {
+            let mut rest = queen_attacks(square, !empty) & empty & !seen;
+            seen |= rest;
+            while rest != 0 {
+                todo.push(rest.trailing_zeros() as u8);
+                rest &= rest - 1;
+            }
+        }
+        seen
+    }
+
+    fn component_count(graph: u64) -> u32 {
+        let mut rest = graph;
+        let mut count = 0;
+        while rest != 0 {
+            let seed = rest & rest.wrapping_neg();
+            rest &= !flood_fill(seed, graph);
+            count += 1;
+        }
+        count
+    }
+
+    #[test]
+    fn test_reachable_matches_queen_moves() {
+        let mut rng = rng_from_seed(Some(12));
+        for _ in 0..500 {
+            let state = random_state(&mut rng);
+            assert_eq!(reachable(&state, state.wqueen()), reachable_slow(&state, state.wqueen()), "{}", state);
+            assert_eq!(reachable(&state, state.bqueen()), reachable_slow(&state, state.bqueen()), "{}", state);
+        }
+    }
+
+    #[test]
+    fn test_articulation_matches_brute_force() {
+        let mut rng = rng_from_seed(Some(13));
+        for _ in 0..300 {
+            let state = random_state(&mut rng);
+            let region = reachable(&state, state.wqueen());
+            let graph = region | 1u64 << state.wqueen();
+            let mut expected = 0u64;
+            for square in 0..64 {
+                if region >> square & 1 == 1
+                    && component_count(graph & !(1u64 << square)) > component_count(graph) {
+                    expected |= 1u64 << square;
+                }
+            }
+            assert_eq!(articulation_squares(state.wqueen(), region), expected, "{}", state);
+        }
+    }
+
+    #[test]
+    fn test_separated_queens() {
+        // A wall of blocks across rank 5 splits the board.
+        let state = vgs("
+            ...B....
+            ........
+            ........
+            ########
+            ........
+            ........
+            ........
+            ....W...
+        ", true);
+        let regions = Regions::of(&state);
+        assert!(!regions.shared);
+        assert_eq!(regions.white_size(), 31);
+        assert_eq!(regions.black_size(), 23);
+        assert_eq!(regions.articulation, 0);
+    }
+
+    #[test]
+    fn test_shared_region() {
+        let state = GameState::def();
+        let regions = Regions::of(&state);
+        assert!(regions.shared);
+        assert_eq!(regions.white, regions.black);
+        assert_eq!(regions.white_size(), 62);
+
+        // Adjacent queens share a region even with nothing else open.
+        let state = vgs("
+            ########
+            ########
+            ########
+            ########
+            ########
+            ########
+            ##BW####
+            ########
+        ", true);
+        assert!(queens_share_region(&state));
+        assert_eq!(Regions::of(&state).white_size(), 0);
+    }
+
+    #[test]
+    fn test_corridor_articulation() {
+        // The white queen's region is a corridor: every square but the last
+        // one links the rest.
+        let state = vgs("
+            ########
+            ########
+            ########
+            ########
+            ###B####
+            ########
+            ########
+            W...####
+        ", true);
+        let regions = Regions::of(&state);
+        assert!(!regions.shared);
+        assert_eq!(regions.white_size(), 3);
+        assert_eq!(regions.articulation, 1 << 1 | 1 << 2);
+    }
+}
@@ -0,0 +1,429 @@
+use std::fmt;
+use std::str::FromStr;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use crate::qd::legalcomp::get_possible_legal_moves;
+use crate::qd::notation::{parse_square, square_name};
+use crate::qd::state::GameState;
+use crate::qd::symmetry::Transform;
+use crate::rng::with_rng;
+
+/// How many tries `generate` gets before it gives up on the reject filter.
+pub const MAX_ATTEMPTS: usize = 10_000;
+
+/// The deepest `reject=N` filter a string may ask for. The filter is an
+/// exhaustive search run on every attempt, and each extra ply makes it
+/// roughly ten times slower.
+pub const MAX_REJECT_PLIES: u32 = 4;
+
+/// How the blocks are counted.
+#[derive(PartialEq)]
+#[derive(Debug, Eq)]
+#[derive(Copy, Clone)]
+pub enum BlockCount {
+    /// Draws this many random squares. Repeated draws and draws on a queen
+    /// square are lost, so the position has at most this many blocks (twice
+    /// as many with a symmetry).
+    Draws(u8),
+    /// Exactly this many blocks.
+    Exact(u8),
+}
+
+/// Symmetry of the block layout.
+#[derive(PartialEq)]
+#[derive(Debug, Eq)]
+#[derive(Copy, Clone)]
+pub enum Symmetry {
+    None,
+    /// Reflected across the middle of the board, rank 1 onto rank 8.
+    Mirror,
+    /// Rotated half a turn around the centre, a1 onto h8.
+    Point,
+}
+
+impl Symmetry {
+    pub fn image(self, square: u8) -> u8 {
+        match self {
+            Symmetry::None => square,
+            Symmetry::Mirror => Transform::FlipVertical.apply_square(square),
+            Symmetry::Point => Transform::Rotate180.apply_square(square),
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Symmetry::None => "none",
+            Symmetry::Mirror => "mirror",
+            Symmetry::Point => "point",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StartPosError {
+    BadOption(String),
+    BadValue { key: String, value: String },
+    QueensOverlap,
+    TooManyBlocks { requested: u8, available: u8 },
+    OddSymmetricBlocks(u8),
+    NoAcceptablePosition,
+    RejectTooDeep { plies: u32, max: u32 },
+}
+
+impl fmt::Display for StartPosError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StartPosError::BadOption(option) =>
+                write!(f, "unknown start position option \"{}\"", option),
+            StartPosError::BadValue { key, value } =>
+                write!(f, "\"{}\" is not a valid value for {}", value, key),
+            StartPosError::QueensOverlap => write!(f, "both queens start on the same square"),
+            StartPosError::TooManyBlocks { requested, available } =>
+                write!(f, "{} blocks requested but only {} squares are free", requested, available),
+            StartPosError::OddSymmetricBlocks(n) =>
+                write!(f, "a symmetric layout needs an even number of blocks, not {}", n),
+            StartPosError::NoAcceptablePosition =>
+                write!(f, "no acceptable position found in {} attempts", MAX_ATTEMPTS),
+            StartPosError::RejectTooDeep { plies, max } =>
+                write!(f, "reject={} searches too deep, at most {} plies are allowed", plies, max),
+        }
+    }
+}
+
+impl std::error::Error for StartPosError {}
+
+/// Random start positions for bot games.
+///
+/// The default reproduces `GameState::def_rand`: queens on e1 and d8, seven
+/// block draws, no symmetry and no filter. As a string (see `FromStr`) the
+/// options are comma-separated `key=value` pairs:
+///
+/// - `blocks=N` for exactly N blocks, or `draws=N` for N draws
+/// - `symmetry=none|mirror|point`
+/// - `white=e1` and `black=d8` for the queen squares
+/// - `reject=N` to reject positions whose result is forced within N plies,
+///   for N up to `MAX_REJECT_PLIES`
+///
+/// `default` stands for the default generator.
+#[derive(PartialEq)]
+#[derive(Debug, Eq)]
+#[derive(Copy, Clone)]
+pub struct StartPositionGenerator {
+    pub blocks: BlockCount,
+    pub symmetry: Symmetry,
+    pub wqueen: u8,
+    pub bqueen: u8,
+    pub reject_within: Option<u32>,
+}
+
+impl Default for StartPositionGenerator {
+    fn default() -> Self {
+        Self {
+            blocks: BlockCount::Draws(7),
+            symmetry: Symmetry::None,
+            wqueen: 4,
+            bqueen: 59,
+            reject_within: None,
+        }
+    }
+}
+
+impl StartPositionGenerator {
+    pub fn validate(&self) -> Result<(), StartPosError> {
+        if self.wqueen == self.bqueen {
+            return Err(StartPosError::QueensOverlap);
+        }
+        if let BlockCount::Exact(n) = self.blocks {
+            if self.symmetry != Symmetry::None && n % 2 == 1 {
+                return Err(StartPosError::OddSymmetricBlocks(n));
+            }
+            let available = self.candidates().len() as u8 * self.blocks_per_candidate();
+            if n > available {
+                return Err(StartPosError::TooManyBlocks { requested: n, available });
+            }
+        }
+        Ok(())
+    }
+
+    fn is_queen_square(&self, square: u8) -> bool {
+        square == self.wqueen || square == self.bqueen
+    }
+
+    fn blocks_per_candidate(&self) -> u8 {
+        if self.symmetry == Symmetry::None { 1 } else { 2 }
+    }
+
+    /// Squares that may be blocked, one per pair of symmetric squares.
+    fn candidates(&self) -> Vec<u8> {
+        (0..64u8)
+            .filter(|&square| square <= self.symmetry.image(square))
+            .filter(|&square| {
+                !self.is_queen_square(square) && !self.is_queen_square(self.symmetry.image(square))
+            })
+            .collect()
+    }
+
+    fn block_with_image(&self, blocks: u64, square: u8) -> u64 {
+        blocks | 1 << square | 1 << self.symmetry.image(square)
+    }
+
+    fn draw_blocks<R: Rng + ?Sized>(&self, rng: &mut R) -> u64 {
+        match self.blocks {
+            BlockCount::Draws(n) => {
+                let mut blocks: u64 = 0;
+                for _ in 0..n {
+                    let square: u32 = rng.gen_range(0..64);
+                    blocks = self.block_with_image(blocks, square as u8);
+                }
+                let queens = 1u64 << self.wqueen | 1u64 << self.bqueen;
+                let queen_images = 1u64 << self.symmetry.image(self.wqueen)
+                    | 1u64 << self.symmetry.image(self.bqueen);
+                blocks & !queens & !queen_images
+            }
+            BlockCount::Exact(n) => {
+                let candidates = self.candidates();
+                candidates
+                    .choose_multiple(rng, (n / self.blocks_per_candidate()) as usize)
+                    .fold(0, |blocks, &square| self.block_with_image(blocks, square))
+            }
+        }
+    }
+
+    /// Generates a position with the thread's generator.
+    ///
+    /// Panics if the options are invalid or nothing passes the filter.
+    pub fn generate(&self) -> GameState {
+        with_rng(|rng| self.generate_with(rng))
+    }
+
+    pub fn generate_with<R: Rng + ?Sized>(&self, rng: &mut R) -> GameState {
+        self.try_generate_with(rng).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_generate_with<R: Rng + ?Sized>(&self, rng: &mut R) -> Result<GameState, StartPosError> {
+        self.validate()?;
+        let mut state = GameState::new(Some(self.wqueen), Some(self.bqueen), None, Some(true));
+        for _ in 0..MAX_ATTEMPTS {
+            state.set_blocks(self.draw_blocks(rng));
+            match self.reject_within {
+                Some(plies) if forced_result(&state, plies).is_some() => continue,
+                _ => return Ok(state),
+            }
+        }
+        Err(StartPosError::NoAcceptablePosition)
+    }
+}
+
+/// The winner if one side can force the result within `plies` plies.
+pub fn forced_result(state: &GameState, plies: u32) -> Option<bool> {
+    if let Some(winner) = state.result() {
+        return Some(winner);
+    }
+    if plies == 0 {
+        return None;
+    }
+    let mover = state.is_white_turn();
+    let mut all_lose = true;
+    let mut rest = get_possible_legal_moves(state);
+    while rest != 0 {
+        let to = rest.trailing_zeros() as u8;
+        rest &= rest - 1;
+        let mut child = *state;
+        child.make_move(to);
+        match forced_result(&child, plies - 1) {
+            Some(winner) if winner == mover => return Some(mover),
+            Some(_) => {}
+            None => all_lose = false,
+        }
+    }
+    if all_lose { Some(!mover) } else { None }
+}
+
+impl fmt::Display for StartPositionGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let default = Self::default();
+        let mut options = Vec::new();
+        if self.blocks != default.blocks {
+            options.push(match self.blocks {
+                BlockCount::Draws(n) => format!("draws={}", n),
+                BlockCount::Exact(n) => format!("blocks={}", n),
+            });
+        }
+        if self.symmetry != default.symmetry {
+            options.push(format!("symmetry={}", self.symmetry.as_str()));
+        }
+        if self.wqueen != default.wqueen {
+            options.push(format!("white={}", square_name(self.wqueen)));
+        }
+        if self.bqueen != default.bqueen {
+            options.push(format!("black={}", square_name(self.bqueen)));
+        }
+        if let Some(plies) = self.reject_within {
+            options.push(format!("reject={}", plies));
+        }
+        if options.is_empty() {
+            write!(f, "default")
+        } else {
+            write!(f, "{}", options.join(","))
+        }
+    }
+}
+
+impl FromStr for StartPositionGenerator {
+    type Err = StartPosError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut generator = Self::default();
+        let s = s.trim();
+        if s.is_empty() || s == "default" {
+            return Ok(generator);
+        }
+        for option in s.split(',') {
+            let (key, value) = option
+                .split_once('=')
+                .ok_or_else(|| StartPosError::BadOption(option.to_string()))?;
+            let (key, value) = (key.trim(), value.trim());
+            let bad_value = || StartPosError::BadValue { key: key.to_string(), value: value.to_string() };
+            match key {
+                "blocks" => generator.blocks = BlockCount::Exact(value.parse().map_err(|_| bad_value())?),
+                "draws" => generator.blocks = BlockCount::Draws(value.parse().map_err(|_| bad_value())?),
+                "symmetry" => generator.symmetry = match value {
+                    "none" => Symmetry::None,
+                    "mirror" => Symmetry::Mirror,
+                    "point" => Symmetry::Point,
+                    _ => return Err(bad_value()),
+                },
+                "white" => generator.wqueen = parse_square(value).ok_or_else(bad_value)?,
+                "black" => generator.bqueen = parse_square(value).ok_or_else(bad_value)?,
+                "reject" => {
+                    let plies = value.parse().map_err(|_| bad_value())?;
+                    if plies > MAX_REJECT_PLIES {
+                        return Err(StartPosError::RejectTooDeep { plies, max: MAX_REJECT_PLIES });
+                    }
+                    generator.reject_within = Some(plies);
+                }
+                _ => return Err(StartPosError::BadOption(key.to_string())),
+            }
+        }
+        generator.validate()?;
+        Ok(generator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::rng_from_seed;
+
+    fn generate_many(generator: &StartPositionGenerator) -> Vec<GameState> {
+        let mut rng = rng_from_seed(Some(3));
+        (0..200).map(|_| generator.generate_with(&mut rng)).collect()
+    }
+
+    #[test]
+    fn test_default_matches_def_rand() {
+        let mut a = rng_from_seed(Some(9));
+        let mut b = rng_from_seed(Some(9));
+        for _ in 0..50 {
+            let state = StartPositionGenerator::default().generate_with(&mut a);
+            let mut blocks: u64 = 0;
+            for _ in 0..7 {
+                blocks |= 1 << b.gen_range(0..64);
+            }
+            blocks &= !(1u64 << 4) & !(1u64 << 59);
+            assert_eq!(state, GameState::new(Some(4), Some(59), Some(blocks), Some(true)));
+        }
+    }
+
+    #[test]
+    fn test_exact_blocks() {
+        for symmetry in [Symmetry::None, Symmetry::Mirror, Symmetry::Point] {
+            let generator = StartPositionGenerator {
+                blocks: BlockCount::Exact(10),
+                symmetry,
+                ..Default::default()
+            };
+            for state in generate_many(&generator) {
+                assert_eq!(state.blocks().count_ones(), 10);
+                assert_eq!(state.blocks() & (1 << 4 | 1 << 59), 0);
+                state.validate().unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_symmetric_layouts() {
+        for symmetry in [Symmetry::Mirror, Symmetry::Point] {
+            let generator = StartPositionGenerator {
+                blocks: BlockCount::Draws(6),
+                symmetry,
+                ..Default::default()
+            };
+            for state in generate_many(&generator) {
+                for square in 0..64 {
+                    let blocked = state.blocks() >> square & 1;
+                    assert_eq!(blocked, state.blocks() >> symmetry.image(square) & 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_custom_queens() {
+        let generator: StartPositionGenerator = "white=a1,black=h8,blocks=20".parse().unwrap();
+        for state in generate_many(&generator) {
+            assert_eq!((state.wqueen(), state.bqueen()), (0, 63));
+            assert_eq!(state.blocks().count_ones(), 20);
+            assert_eq!(state.blocks() & (1 << 0 | 1 << 63), 0);
+        }
+    }
+
+    #[test]
+    fn test_reject_filter() {
+        let generator: StartPositionGenerator = "blocks=30,reject=2".parse().unwrap();
+        for state in generate_many(&generator) {
+            assert_eq!(forced_result(&state, 2), None);
+        }
+        // Queens facing each other: white captures at once.
+        let facing: StartPositionGenerator = "white=d1,black=d8,blocks=0,reject=1".parse().unwrap();
+        let mut rng = rng_from_seed(Some(1));
+        assert_eq!(facing.try_generate_with(&mut rng), Err(StartPosError::NoAcceptablePosition));
+    }
+
+    #[test]
+    fn test_forced_result() {
+        let facing = GameState::new(Some(3), Some(59), None, Some(true));
+        assert_eq!(forced_result(&facing, 0), None);
+        assert_eq!(forced_result(&facing, 1), Some(true));
+        assert_eq!(forced_result(&GameState::def(), 4), None);
+        let over = GameState::new(Some(12), Some(12), None, Some(false));
+        assert_eq!(forced_result(&over, 0), Some(true));
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        assert_eq!("default".parse::<StartPositionGenerator>(), Ok(StartPositionGenerator::default()));
+        assert_eq!(StartPositionGenerator::default().to_string(), "default");
+        let spec = "blocks=8,symmetry=point,white=c3,black=f6,reject=2";
+        let generator: StartPositionGenerator = spec.parse().unwrap();
+        assert_eq!(generator.to_string(), spec);
+        assert_eq!(generator.to_string().parse(), Ok(generator));
+
+        assert_eq!("size=3".parse::<StartPositionGenerator>(), Err(StartPosError::BadOption("size".to_string())));
+        assert!(matches!("symmetry=x".parse::<StartPositionGenerator>(), Err(StartPosError::BadValue { .. })));
+        assert!(matches!("white=z9".parse::<StartPositionGenerator>(), Err(StartPosError::BadValue { .. })));
+        assert_eq!("white=d8".parse::<StartPositionGenerator>(), Err(StartPosError::QueensOverlap));
+        assert_eq!(
+            "blocks=7,symmetry=mirror".parse::<StartPositionGenerator>(),
+            Err(StartPosError::OddSymmetricBlocks(7))
+        );
+        assert_eq!(
+            "blocks=63".parse::<StartPositionGenerator>(),
+            Err(StartPosError::TooManyBlocks { requested: 63, available: 62 })
+        );
+        assert_eq!(
+            "reject=30".parse::<StartPositionGenerator>(),
+            Err(StartPosError::RejectTooDeep { plies: 30, max: MAX_REJECT_PLIES })
+        );
+    }
+}
@@ -1,59 +1,235 @@
+use std::hash::{Hash, Hasher};
 use rand::Rng;
 use crate::qd::legalcomp::{
     get_possible_legal_moves
 };
+use crate::qd::error::QdError;
+use crate::qd::startpos::StartPositionGenerator;
+use crate::qd::zobrist;
+use crate::rng::with_rng;
 
+#[derive(PartialEq)]
+#[derive(Debug, Eq)]
+#[derive(Copy, Clone)]
+pub struct Move {
+    pub from: u8,
+    pub to: u8,
+}
+
+/// Everything `GameState::unmake_move` needs to take a move back.
+#[derive(PartialEq)]
+#[derive(Debug, Eq)]
+#[derive(Copy, Clone)]
+pub struct Undo {
+    pub mv: Move,
+    pub from_was_blocked: bool,
+}
+
+/// A position. The fields are private so the Zobrist key always matches
+/// them; change them through the setters, which update the key.
 #[derive(PartialEq)]
 #[derive(Debug, Eq)]
 #[derive(Copy, Clone)]
 pub struct GameState {
-    pub wqueen: u8,
-    pub bqueen: u8,
-    pub blocks: u64,
-    pub is_white_turn: bool,
+    wqueen: u8,
+    bqueen: u8,
+    blocks: u64,
+    is_white_turn: bool,
+    zobrist: u64,
+}
+
+impl Hash for GameState {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.zobrist);
+    }
 }
 
 impl GameState {
+    /// Builds a position, defaulting to the standard start. Panics if a
+    /// queen square is 64 or more; see `try_new`.
     pub fn new(
         wqueen: Option<u8>,
         bqueen: Option<u8>,
         blocks: Option<u64>,
         is_white_turn: Option<bool>,
     ) -> Self {
-        Self {
+        let mut state = Self {
             wqueen: wqueen.unwrap_or(4),
             bqueen: bqueen.unwrap_or(59),
             blocks: blocks.unwrap_or(0),
             is_white_turn: is_white_turn.unwrap_or(true),
+            zobrist: 0,
+        };
+        assert!(state.wqueen < 64 && state.bqueen < 64, "queen square out of range");
+        state.zobrist = state.compute_zobrist();
+        state
+    }
+
+    /// Like `new`, but rejects positions that `validate` would reject
+    /// instead of building them.
+    pub fn try_new(
+        wqueen: Option<u8>,
+        bqueen: Option<u8>,
+        blocks: Option<u64>,
+        is_white_turn: Option<bool>,
+    ) -> Result<Self, QdError> {
+        for square in [wqueen, bqueen].into_iter().flatten() {
+            if square >= 64 {
+                return Err(QdError::SquareOutOfRange(square));
+            }
+        }
+        let state = Self::new(wqueen, bqueen, blocks, is_white_turn);
+        state.validate()?;
+        Ok(state)
+    }
+
+    /// Checks that the position could occur in a game that is still
+    /// running: both queens on the board, on open squares and apart.
+    pub fn validate(&self) -> Result<(), QdError> {
+        for square in [self.wqueen, self.bqueen] {
+            if square >= 64 {
+                return Err(QdError::SquareOutOfRange(square));
+            }
+        }
+        if self.blocks & (1 << self.wqueen) != 0 {
+            return Err(QdError::QueenOnBlock { white: true });
         }
+        if self.blocks & (1 << self.bqueen) != 0 {
+            return Err(QdError::QueenOnBlock { white: false });
+        }
+        if self.wqueen == self.bqueen {
+            return Err(QdError::QueensOverlap);
+        }
+        Ok(())
+    }
+
+    pub fn wqueen(&self) -> u8 {
+        self.wqueen
+    }
+
+    pub fn bqueen(&self) -> u8 {
+        self.bqueen
+    }
+
+    pub fn blocks(&self) -> u64 {
+        self.blocks
+    }
+
+    pub fn is_white_turn(&self) -> bool {
+        self.is_white_turn
+    }
+
+    /// Moves the white queen without playing a move. Panics if `square`
+    /// is 64 or more.
+    pub fn set_wqueen(&mut self, square: u8) {
+        self.zobrist ^= zobrist::WQUEEN_KEYS[self.wqueen as usize] ^ zobrist::WQUEEN_KEYS[square as usize];
+        self.wqueen = square;
+    }
+
+    /// Moves the black queen without playing a move. Panics if `square`
+    /// is 64 or more.
+    pub fn set_bqueen(&mut self, square: u8) {
+        self.zobrist ^= zobrist::BQUEEN_KEYS[self.bqueen as usize] ^ zobrist::BQUEEN_KEYS[square as usize];
+        self.bqueen = square;
+    }
+
+    pub fn set_blocks(&mut self, blocks: u64) {
+        self.zobrist ^= zobrist::blocks_key(self.blocks ^ blocks);
+        self.blocks = blocks;
+    }
+
+    pub fn set_white_turn(&mut self, is_white_turn: bool) {
+        if self.is_white_turn != is_white_turn {
+            self.zobrist ^= zobrist::BLACK_TO_MOVE_KEY;
+        }
+        self.is_white_turn = is_white_turn;
+    }
+
+    /// The incrementally maintained Zobrist key of this position.
+    pub fn zobrist(&self) -> u64 {
+        self.zobrist
+    }
+
+    pub fn compute_zobrist(&self) -> u64 {
+        zobrist::compute(self.wqueen, self.bqueen, self.blocks, self.is_white_turn)
     }
 
     pub fn def() -> Self {
         Self::new(None, None, None, None)
     }
 
+    /// A random start from the default `StartPositionGenerator`, drawn from
+    /// this thread's shared generator.
     pub fn def_rand() -> Self {
-        let mut blocks: u64 = 0;
-        let mut rng = rand::thread_rng();
-        for _ in 0..7 {
-            blocks |= 1 << rng.gen_range(0..64);
-        }
-        blocks &= !(1u64 << 4) & !(1u64 << 59);
-        Self::new(Some(4), Some(59), Some(blocks), Some(true))
+        with_rng(Self::def_rand_with)
     }
 
+    pub fn def_rand_with<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        StartPositionGenerator::default().generate_with(rng)
+    }
+
+    /// Plays `to`. Panics if the move is illegal; see `try_make_move`.
     pub fn make_move(&mut self, to: u8) {
-        assert!(to < 64);
-        assert_ne!(get_possible_legal_moves(self) & (1 << to), 0);
-        
+        self.make_move_undoable(to);
+    }
+
+    pub fn make_move_undoable(&mut self, to: u8) -> Undo {
+        self.try_make_move(to).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_make_move(&mut self, to: u8) -> Result<Undo, QdError> {
+        if to >= 64 {
+            return Err(QdError::SquareOutOfRange(to));
+        }
+        if self.wqueen == self.bqueen {
+            return Err(QdError::GameOver);
+        }
+        let legal_moves = get_possible_legal_moves(self);
+        if legal_moves == 0 {
+            return Err(QdError::GameOver);
+        }
+        if legal_moves & (1 << to) == 0 {
+            return Err(QdError::IllegalMove(to));
+        }
+
+        let from = if self.is_white_turn { self.wqueen } else { self.bqueen };
+        let from_was_blocked = self.blocks & (1 << from) != 0;
+
         if self.is_white_turn {
             self.blocks |= 1 << self.wqueen;
             self.wqueen = to;
+            self.zobrist ^= zobrist::WQUEEN_KEYS[from as usize] ^ zobrist::WQUEEN_KEYS[to as usize];
         } else {
             self.blocks |= 1 << self.bqueen;
             self.bqueen = to;
+            self.zobrist ^= zobrist::BQUEEN_KEYS[from as usize] ^ zobrist::BQUEEN_KEYS[to as usize];
+        }
+        if !from_was_blocked {
+            self.zobrist ^= zobrist::BLOCK_KEYS[from as usize];
         }
         self.is_white_turn = !self.is_white_turn;
+        self.zobrist ^= zobrist::BLACK_TO_MOVE_KEY;
+
+        Ok(Undo { mv: Move { from, to }, from_was_blocked })
+    }
+
+    pub fn unmake_move(&mut self, undo: Undo) {
+        let Undo { mv: Move { from, to }, from_was_blocked } = undo;
+        self.is_white_turn = !self.is_white_turn;
+        self.zobrist ^= zobrist::BLACK_TO_MOVE_KEY;
+        if self.is_white_turn {
+            assert_eq!(self.wqueen, to);
+            self.wqueen = from;
+            self.zobrist ^= zobrist::WQUEEN_KEYS[from as usize] ^ zobrist::WQUEEN_KEYS[to as usize];
+        } else {
+            assert_eq!(self.bqueen, to);
+            self.bqueen = from;
+            self.zobrist ^= zobrist::BQUEEN_KEYS[from as usize] ^ zobrist::BQUEEN_KEYS[to as usize];
+        }
+        if !from_was_blocked {
+            self.blocks &= !(1 << from);
+            self.zobrist ^= zobrist::BLOCK_KEYS[from as usize];
+        }
     }
 
     pub fn result(&self) -> Option<bool> {
@@ -115,6 +291,147 @@ mod tests {
         assert_eq!(state.result(), Some(false));
     }
 
+    #[test]
+    fn test_unmake_move() {
+        let mut state = GameState::def();
+        let start = state;
+
+        let undo_1 = state.make_move_undoable(3);
+        assert_eq!(undo_1.mv, Move { from: 4, to: 3 });
+        let after_1 = state;
+        let undo_2 = state.make_move_undoable(58);
+        let undo_3 = state.make_move_undoable(2);
+
+        state.unmake_move(undo_3);
+        state.unmake_move(undo_2);
+        assert_eq!(state, after_1);
+        state.unmake_move(undo_1);
+        assert_eq!(state, start);
+    }
+
+    #[test]
+    fn test_unmake_move_random() {
+        for _ in 0..100 {
+            let mut state = GameState::def_rand();
+            let mut history = Vec::new();
+            let mut undos = Vec::new();
+            while state.result().is_none() {
+                let legal_moves = get_possible_legal_moves(&state);
+                let to = legal_moves.trailing_zeros() as u8;
+                history.push(state);
+                undos.push(state.make_move_undoable(to));
+            }
+            while let Some(undo) = undos.pop() {
+                state.unmake_move(undo);
+                assert_eq!(state, history.pop().unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_zobrist_incremental() {
+        for _ in 0..100 {
+            let mut state = GameState::def_rand();
+            let mut undos = Vec::new();
+            let mut hashes = Vec::new();
+            while state.result().is_none() {
+                assert_eq!(state.zobrist(), state.compute_zobrist());
+                let legal_moves = get_possible_legal_moves(&state);
+                let to = 63 - legal_moves.leading_zeros() as u8;
+                hashes.push(state.zobrist());
+                undos.push(state.make_move_undoable(to));
+            }
+            assert_eq!(state.zobrist(), state.compute_zobrist());
+            while let Some(undo) = undos.pop() {
+                state.unmake_move(undo);
+                assert_eq!(state.zobrist(), hashes.pop().unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_zobrist_distinguishes() {
+        let state = GameState::def();
+        let black_turn = GameState::new(None, None, None, Some(false));
+        let blocked = GameState::new(None, None, Some(1), None);
+        let swapped = GameState::new(Some(59), Some(4), None, None);
+        assert_ne!(state.zobrist(), black_turn.zobrist());
+        assert_ne!(state.zobrist(), blocked.zobrist());
+        assert_ne!(state.zobrist(), swapped.zobrist());
+    }
+
+    #[test]
+    fn test_setters_keep_zobrist() {
+        let mut state = GameState::def();
+        state.set_wqueen(10);
+        state.set_bqueen(20);
+        state.set_blocks(0xff00);
+        state.set_white_turn(false);
+        assert_eq!(state, GameState::new(Some(10), Some(20), Some(0xff00), Some(false)));
+        assert_eq!(state.zobrist(), state.compute_zobrist());
+        state.set_blocks(0x0ff0);
+        assert_eq!(state.zobrist(), state.compute_zobrist());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_new_rejects_square() {
+        GameState::new(Some(64), None, None, None);
+    }
+
+    #[test]
+    fn test_try_make_move() {
+        let mut state = GameState::def();
+        assert_eq!(state.try_make_move(64), Err(QdError::SquareOutOfRange(64)));
+        assert_eq!(state.try_make_move(14), Err(QdError::IllegalMove(14)));
+        assert_eq!(state, GameState::def());
+        assert!(state.try_make_move(12).is_ok());
+
+        let mut captured = GameState::new(Some(12), Some(12), None, Some(false));
+        assert_eq!(captured.try_make_move(13), Err(QdError::GameOver));
+
+        let mut stuck = GameState::new(Some(0), Some(63), Some(vbb("
+            ........
+            ........
+            ........
+            ........
+            ........
+            ........
+            ##......
+            .#......
+        ")), Some(true));
+        assert_eq!(stuck.try_make_move(1), Err(QdError::GameOver));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(GameState::def().validate(), Ok(()));
+        assert_eq!(
+            GameState::try_new(Some(64), None, None, None),
+            Err(QdError::SquareOutOfRange(64))
+        );
+        assert_eq!(
+            GameState::try_new(None, Some(200), None, None),
+            Err(QdError::SquareOutOfRange(200))
+        );
+        assert_eq!(
+            GameState::try_new(None, None, Some(1 << 4), None),
+            Err(QdError::QueenOnBlock { white: true })
+        );
+        assert_eq!(
+            GameState::try_new(None, None, Some(1 << 59), None),
+            Err(QdError::QueenOnBlock { white: false })
+        );
+        assert_eq!(
+            GameState::try_new(Some(10), Some(10), None, None),
+            Err(QdError::QueensOverlap)
+        );
+        assert_eq!(
+            GameState::try_new(Some(10), Some(20), Some(1), Some(false)),
+            Ok(GameState::new(Some(10), Some(20), Some(1), Some(false)))
+        );
+    }
+
     #[test]
     #[should_panic]
     fn test_illegal_move_1() {
@@ -126,7 +443,7 @@ mod tests {
     #[should_panic]
     fn test_illegal_move_2() {
         let mut state = GameState::def();
-        state.blocks = vbb("
+        state.set_blocks(vbb("
             ....#...
             ...#....
             ........
@@ -135,7 +452,7 @@ mod tests {
             ..#.....
             .##...#.
             ........
-        ");
+        "));
         state.make_move(8 * 7 + 4);
     }
 }
\ No newline at end of file
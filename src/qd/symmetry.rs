@@ -0,0 +1,228 @@
+use crate::qd::state::GameState;
+
+/// One of the 8 symmetries of the board. Queen moves look the same under
+/// all of them, so a position and its images have the same game value.
+#[derive(PartialEq)]
+#[derive(Debug, Eq)]
+#[derive(Copy, Clone)]
+pub enum Transform {
+    Identity,
+    /// Quarter turn clockwise, a1 onto a8.
+    Rotate90,
+    Rotate180,
+    /// Quarter turn anticlockwise, a1 onto h1.
+    Rotate270,
+    /// Rank 1 onto rank 8.
+    FlipVertical,
+    /// File a onto file h.
+    FlipHorizontal,
+    /// Reflection in the a1-h8 diagonal.
+    FlipDiagonal,
+    /// Reflection in the a8-h1 diagonal.
+    FlipAntiDiagonal,
+}
+
+pub const TRANSFORMS: [Transform; 8] = [
+    Transform::Identity,
+    Transform::Rotate90,
+    Transform::Rotate180,
+    Transform::Rotate270,
+    Transform::FlipVertical,
+    Transform::FlipHorizontal,
+    Transform::FlipDiagonal,
+    Transform::FlipAntiDiagonal,
+];
+
+pub fn flip_vertical(bb: u64) -> u64 {
+    bb.swap_bytes()
+}
+
+pub fn flip_horizontal(bb: u64) -> u64 {
+    u64::from_le_bytes(bb.to_le_bytes().map(u8::reverse_bits))
+}
+
+pub fn flip_diagonal(bb: u64) -> u64 {
+    let mut bb = bb;
+    let t = 0x0f0f0f0f00000000 & (bb ^ (bb << 28));
+    bb ^= t ^ (t >> 28);
+    let t = 0x3333000033330000 & (bb ^ (bb << 14));
+    bb ^= t ^ (t >> 14);
+    let t = 0x5500550055005500 & (bb ^ (bb << 7));
+    bb ^= t ^ (t >> 7);
+    bb
+}
+
+impl Transform {
+    pub fn apply_square(self, square: u8) -> u8 {
+        let (rank, file) = (square / 8, square % 8);
+        let (rank, file) = match self {
+            Transform::Identity => (rank, file),
+            Transform::Rotate90 => (7 - file, rank),
+            Transform::Rotate180 => (7 - rank, 7 - file),
+            Transform::Rotate270 => (file, 7 - rank),
+            Transform::FlipVertical => (7 - rank, file),
+            Transform::FlipHorizontal => (rank, 7 - file),
+            Transform::FlipDiagonal => (file, rank),
+            Transform::FlipAntiDiagonal => (7 - file, 7 - rank),
+        };
+        rank * 8 + file
+    }
+
+    pub fn apply_bitboard(self, bb: u64) -> u64 {
+        match self {
+            Transform::Identity => bb,
+            Transform::Rotate90 => flip_vertical(flip_diagonal(bb)),
+            Transform::Rotate180 => bb.reverse_bits(),
+            Transform::Rotate270 => flip_diagonal(flip_vertical(bb)),
+            Transform::FlipVertical => flip_vertical(bb),
+            Transform::FlipHorizontal => flip_horizontal(bb),
+            Transform::FlipDiagonal => flip_diagonal(bb),
+            Transform::FlipAntiDiagonal => flip_diagonal(bb).reverse_bits(),
+        }
+    }
+
+    pub fn inverse(self) -> Self {
+        match self {
+            Transform::Rotate90 => Transform::Rotate270,
+            Transform::Rotate270 => Transform::Rotate90,
+            other => other,
+        }
+    }
+}
+
+impl GameState {
+    pub fn transformed(&self, transform: Transform) -> Self {
+        Self::new(
+            Some(transform.apply_square(self.wqueen())),
+            Some(transform.apply_square(self.bqueen())),
+            Some(transform.apply_bitboard(self.blocks())),
+            Some(self.is_white_turn()),
+        )
+    }
+
+    /// The same position with the colors swapped: the queens trade places
+    /// and the other side is to move. The side to move keeps its queen, so
+    /// its result is unchanged, but `result()` names the other color.
+    pub fn color_swapped(&self) -> Self {
+        Self::new(Some(self.bqueen()), Some(self.wqueen()), Some(self.blocks()), Some(!self.is_white_turn()))
+    }
+
+    /// The representative of this position's class under the 8 board
+    /// symmetries and the color swap, together with the transform and
+    /// whether the colors were swapped to reach it. Apply the color swap
+    /// first, then the transform.
+    pub fn canonical_form(&self) -> (Self, Transform, bool) {
+        let mut best = None;
+        for swapped in [false, true] {
+            let (wqueen, bqueen, is_white_turn) = if swapped {
+                (self.bqueen(), self.wqueen(), !self.is_white_turn())
+            } else {
+                (self.wqueen(), self.bqueen(), self.is_white_turn())
+            };
+            for transform in TRANSFORMS {
+                let key = (
+                    transform.apply_bitboard(self.blocks()),
+                    transform.apply_square(wqueen),
+                    transform.apply_square(bqueen),
+                    !is_white_turn,
+                );
+                if best.is_none_or(|(best_key, _, _)| key < best_key) {
+                    best = Some((key, transform, swapped));
+                }
+            }
+        }
+        let ((blocks, wqueen, bqueen, black_turn), transform, swapped) = best.unwrap();
+        let state = Self::new(Some(wqueen), Some(bqueen), Some(blocks), Some(!black_turn));
+        (state, transform, swapped)
+    }
+
+    /// Equal for any two positions that differ only by a board symmetry or
+    /// a color swap.
+    pub fn canonical(&self) -> Self {
+        self.canonical_form().0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qd::legalcomp::get_possible_legal_moves;
+    use crate::qd::perft::perft;
+    use crate::rng::rng_from_seed;
+    use rand::Rng;
+
+    fn square_map(transform: Transform, bb: u64) -> u64 {
+        (0..64u8)
+            .filter(|&square| bb >> square & 1 == 1)
+            .fold(0, |res, square| res | 1 << transform.apply_square(square))
+    }
+
+    #[test]
+    fn test_bitboard_matches_squares() {
+        let mut rng = rng_from_seed(Some(4));
+        for transform in TRANSFORMS {
+            for _ in 0..200 {
+                let bb: u64 = rng.r#gen();
+                assert_eq!(transform.apply_bitboard(bb), square_map(transform, bb), "{:?}", transform);
+            }
+        }
+        assert_eq!(Transform::Rotate90.apply_square(0), 56);
+        assert_eq!(Transform::Rotate270.apply_square(0), 7);
+    }
+
+    #[test]
+    fn test_inverse() {
+        for transform in TRANSFORMS {
+            for square in 0..64 {
+                assert_eq!(transform.inverse().apply_square(transform.apply_square(square)), square);
+            }
+        }
+    }
+
+    #[test]
+    fn test_moves_follow_transform() {
+        for _ in 0..100 {
+            let state = GameState::def_rand();
+            for transform in TRANSFORMS {
+                let image = state.transformed(transform);
+                assert_eq!(
+                    get_possible_legal_moves(&image),
+                    transform.apply_bitboard(get_possible_legal_moves(&state))
+                );
+            }
+            let swapped = state.color_swapped();
+            assert_eq!(perft(&swapped, 3), perft(&state, 3));
+        }
+    }
+
+    #[test]
+    fn test_canonical_invariant() {
+        for _ in 0..100 {
+            let state = GameState::def_rand();
+            let canonical = state.canonical();
+            for transform in TRANSFORMS {
+                let image = state.transformed(transform);
+                assert_eq!(image.canonical(), canonical);
+                assert_eq!(image.color_swapped().canonical(), canonical);
+            }
+        }
+    }
+
+    #[test]
+    fn test_canonical_form() {
+        for _ in 0..100 {
+            let state = GameState::def_rand();
+            let (canonical, transform, swapped) = state.canonical_form();
+            let swapped_state = if swapped { state.color_swapped() } else { state };
+            assert_eq!(swapped_state.transformed(transform), canonical);
+            assert_eq!(canonical.zobrist(), canonical.compute_zobrist());
+        }
+    }
+
+    #[test]
+    fn test_color_swap_result() {
+        let state = GameState::new(Some(12), Some(12), None, Some(false));
+        assert_eq!(state.result(), Some(true));
+        assert_eq!(state.color_swapped().result(), Some(false));
+    }
+}
@@ -22,14 +22,14 @@ fn game_state_to_visual(state: &GameState) -> String {
     for rank in (0..8).rev() {
         for file in 0..8 {
             let index = rank * 8 + file;
-            if state.blocks & (1 << index) != 0 {
+            if state.blocks() & (1 << index) != 0 {
                 board.push('#');
-            } else if state.wqueen == index as u8 && state.bqueen == index as u8 {
-                if state.is_white_turn {board.push('B');}
+            } else if state.wqueen() == index as u8 && state.bqueen() == index as u8 {
+                if state.is_white_turn() {board.push('B');}
                 else {board.push('W');}
-            } else if state.wqueen == index as u8 {
+            } else if state.wqueen() == index as u8 {
                 board.push('W');
-            } else if state.bqueen == index as u8 {
+            } else if state.bqueen() == index as u8 {
                 board.push('B');
             } else {
                 board.push('.');
@@ -46,14 +46,14 @@ fn game_state_to_visual_detailed(state: &GameState) -> String {
     for rank in (0..8).rev() {
         for file in 0..8 {
             let index = rank * 8 + file;
-            if state.blocks & (1 << index) != 0 {
+            if state.blocks() & (1 << index) != 0 {
                 board.push('#');
-            } else if state.wqueen == index as u8 && state.bqueen == index as u8 {
-                if state.is_white_turn {board.push('B');}
+            } else if state.wqueen() == index as u8 && state.bqueen() == index as u8 {
+                if state.is_white_turn() {board.push('B');}
                 else {board.push('W');}
-            } else if state.wqueen == index as u8 {
+            } else if state.wqueen() == index as u8 {
                 board.push('W');
-            } else if state.bqueen == index as u8 {
+            } else if state.bqueen() == index as u8 {
                 board.push('B');
             } else {
                 board.push('.');
@@ -240,7 +240,7 @@ mod tests {
                 Some(blocks),
                 Some(is_white_turn)
             );
-            assert_eq!(test_state, vgs(&gsv(&test_state), test_state.is_white_turn));
+            assert_eq!(test_state, vgs(&gsv(&test_state), test_state.is_white_turn()));
         }
     }
 }
\ No newline at end of file
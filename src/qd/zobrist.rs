@@ -0,0 +1,68 @@
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E3779B97F4A7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    (state, z ^ (z >> 31))
+}
+
+const fn gen_keys(seed: u64) -> ([u64; 64], u64) {
+    let mut keys = [0u64; 64];
+    let mut state = seed;
+    let mut i = 0;
+    while i < 64 {
+        let (next, key) = splitmix64(state);
+        keys[i] = key;
+        state = next;
+        i += 1;
+    }
+    (keys, state)
+}
+
+// Keys are generated at compile time from a fixed seed, so hashes are
+// stable across runs and builds.
+const BLOCK_GEN: ([u64; 64], u64) = gen_keys(0x5144_5255_5354_0001);
+const WQUEEN_GEN: ([u64; 64], u64) = gen_keys(BLOCK_GEN.1);
+const BQUEEN_GEN: ([u64; 64], u64) = gen_keys(WQUEEN_GEN.1);
+
+pub const BLOCK_KEYS: [u64; 64] = BLOCK_GEN.0;
+pub const WQUEEN_KEYS: [u64; 64] = WQUEEN_GEN.0;
+pub const BQUEEN_KEYS: [u64; 64] = BQUEEN_GEN.0;
+pub const BLACK_TO_MOVE_KEY: u64 = splitmix64(BQUEEN_GEN.1).1;
+
+pub fn blocks_key(blocks: u64) -> u64 {
+    let mut key = 0;
+    let mut rest = blocks;
+    while rest != 0 {
+        key ^= BLOCK_KEYS[rest.trailing_zeros() as usize];
+        rest &= rest - 1;
+    }
+    key
+}
+
+/// Hashes a position from scratch.
+pub fn compute(wqueen: u8, bqueen: u8, blocks: u64, is_white_turn: bool) -> u64 {
+    let mut key = blocks_key(blocks)
+        ^ WQUEEN_KEYS[wqueen as usize]
+        ^ BQUEEN_KEYS[bqueen as usize];
+    if !is_white_turn {
+        key ^= BLACK_TO_MOVE_KEY;
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_keys_distinct() {
+        let mut seen = HashSet::new();
+        for key in BLOCK_KEYS.iter().chain(WQUEEN_KEYS.iter()).chain(BQUEEN_KEYS.iter()) {
+            assert!(seen.insert(*key));
+        }
+        assert!(seen.insert(BLACK_TO_MOVE_KEY));
+        assert!(!seen.contains(&0));
+    }
+}
@@ -0,0 +1,62 @@
+//! Shared random number source for the engine and the bots.
+//!
+//! Every thread owns one generator. It starts from OS entropy, and
+//! `seed_thread` makes everything that thread draws afterwards
+//! reproducible. Tournaments reseed the worker thread before each game
+//! from a seed derived from the tournament seed, so a game depends only
+//! on its own seed and not on which thread played it.
+
+use std::cell::RefCell;
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+
+pub type QdRng = StdRng;
+
+thread_local! {
+    static THREAD_RNG: RefCell<QdRng> = RefCell::new(QdRng::from_entropy());
+}
+
+/// Resets this thread's generator to a fixed seed.
+pub fn seed_thread(seed: u64) {
+    THREAD_RNG.with(|rng| *rng.borrow_mut() = QdRng::seed_from_u64(seed));
+}
+
+/// Runs `f` with this thread's generator. Calls must not be nested.
+pub fn with_rng<T>(f: impl FnOnce(&mut QdRng) -> T) -> T {
+    THREAD_RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
+
+/// A generator for `seed`, or one seeded from entropy if there is none.
+pub fn rng_from_seed(seed: Option<u64>) -> QdRng {
+    match seed {
+        Some(seed) => QdRng::seed_from_u64(seed),
+        None => QdRng::from_entropy(),
+    }
+}
+
+/// Draws a seed for a sub-task (such as one game) from `rng`.
+pub fn next_seed(rng: &mut QdRng) -> u64 {
+    rng.next_u64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn test_seed_thread() {
+        seed_thread(42);
+        let a: Vec<u32> = (0..10).map(|_| with_rng(|rng| rng.gen_range(0..1000))).collect();
+        seed_thread(42);
+        let b: Vec<u32> = (0..10).map(|_| with_rng(|rng| rng.gen_range(0..1000))).collect();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_rng_from_seed() {
+        let mut a = rng_from_seed(Some(7));
+        let mut b = rng_from_seed(Some(7));
+        assert_eq!(next_seed(&mut a), next_seed(&mut b));
+    }
+}
@@ -0,0 +1,12 @@
+//! Endgame tablebases: every position with at most a few empty squares,
+//! solved exactly.
+//!
+//! A move always fills one empty square (the one the queen lands on) and
+//! blocks the square it left, so each move lowers the number of empty
+//! squares by one. Positions with `e` empty squares therefore only lead to
+//! positions with `e - 1`, and the tables are built backwards from the
+//! full board one level at a time.
+
+pub mod index;
+pub mod generate;
+pub mod probe;
@@ -0,0 +1,139 @@
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+use crate::qd::magic::queen_attacks;
+use crate::tablebase::index::{level_size, TbPosition, MAX_EMPTY_LIMIT};
+use crate::tablebase::probe::{TbEntry, TbError, Tablebase};
+
+/// Solves one position from the solved level below it.
+fn solve_position(pos: &TbPosition, below: &[u8]) -> TbEntry {
+    let blocks = pos.blocks();
+    let moves = queen_attacks(pos.mover, blocks | 1u64 << pos.other) & !blocks;
+    if moves & 1u64 << pos.other != 0 {
+        return TbEntry { win: true, distance: 1 };
+    }
+    let mut best = TbEntry { win: false, distance: 0 };
+    let mut rest = moves;
+    while rest != 0 {
+        let to = rest.trailing_zeros() as u8;
+        rest &= rest - 1;
+        let child = TbPosition { mover: pos.other, other: to, empty: pos.empty & !(1u64 << to) };
+        let entry = TbEntry::from_byte(below[child.index() as usize]).parent();
+        if entry.better_than(&best) {
+            best = entry;
+        }
+    }
+    best
+}
+
+impl Tablebase {
+    /// Solves every position with at most `max_empty` empty squares, level
+    /// by level from the full board. `prog_func` is called with each level
+    /// once it is done.
+    pub fn generate<'a>(
+        max_empty: u8,
+        num_threads: usize,
+        prog_func: &Option<Box<dyn Fn(u8) + 'a>>
+    ) -> Result<Self, TbError> {
+        if max_empty > MAX_EMPTY_LIMIT {
+            return Err(TbError::TooManyEmpty(max_empty));
+        }
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .build()
+            .unwrap();
+        let mut levels: Vec<Vec<u8>> = Vec::new();
+        for level in 0..=max_empty {
+            let below: &[u8] = levels.last().map_or(&[], Vec::as_slice);
+            let solved = pool.install(|| {
+                (0..level_size(level))
+                    .into_par_iter()
+                    .map(|index| solve_position(&TbPosition::at(level, index), below).to_byte())
+                    .collect::<Vec<u8>>()
+            });
+            levels.push(solved);
+            if let Some(prog_func) = prog_func {
+                prog_func(level);
+            }
+        }
+        Ok(Self { max_empty, levels })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::OnceLock;
+    use rand::Rng;
+    use crate::qd::legalcomp::get_possible_legal_moves;
+    use crate::qd::startpos::forced_result;
+    use crate::qd::state::GameState;
+    use crate::rng::rng_from_seed;
+
+    fn tablebase() -> &'static Tablebase {
+        static TABLEBASE: OnceLock<Tablebase> = OnceLock::new();
+        TABLEBASE.get_or_init(|| Tablebase::generate(2, 4, &None).unwrap())
+    }
+
+    fn random_state(rng: &mut impl Rng, empty: u32) -> GameState {
+        let wqueen = rng.gen_range(0..64u8);
+        let bqueen = (wqueen + rng.gen_range(1..64u8)) % 64;
+        let mut blocks = !(1u64 << wqueen) & !(1u64 << bqueen);
+        while (!blocks).count_ones() < empty + 2 {
+            blocks &= !(1u64 << rng.gen_range(0..64));
+        }
+        GameState::new(Some(wqueen), Some(bqueen), Some(blocks), Some(rng.gen_bool(0.5)))
+    }
+
+    #[test]
+    fn test_matches_search() {
+        let tablebase = tablebase();
+        let mut rng = rng_from_seed(Some(41));
+        for _ in 0..3000 {
+            let empty = rng.gen_range(0..=2);
+            let state = random_state(&mut rng, empty);
+            let entry = tablebase.probe(&state).unwrap();
+            let distance = entry.distance as u32;
+            assert_eq!(forced_result(&state, distance), Some(entry.winner(state.is_white_turn())), "{}", state);
+            if distance > 0 {
+                assert_eq!(forced_result(&state, distance - 1), None, "{}", state);
+            }
+        }
+    }
+
+    #[test]
+    fn test_best_move() {
+        let tablebase = tablebase();
+        let mut rng = rng_from_seed(Some(42));
+        for _ in 0..1000 {
+            let state = random_state(&mut rng, 2);
+            let entry = tablebase.probe(&state).unwrap();
+            match tablebase.best_move(&state) {
+                None => assert_eq!(get_possible_legal_moves(&state), 0),
+                Some((to, kept)) => {
+                    assert_eq!(kept, entry);
+                    let mut child = state;
+                    child.make_move(to);
+                    assert_eq!(tablebase.probe(&child).unwrap().parent(), entry, "{}", state);
+                }
+            }
+        }
+        assert_eq!(tablebase.probe(&GameState::def()), None);
+        assert_eq!(tablebase.best_move(&GameState::def()), None);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let tablebase = tablebase();
+        let path = std::env::temp_dir().join(format!("qdrust-tb-test-{}.qdtb", std::process::id()));
+        tablebase.save(&path).unwrap();
+        let loaded = Tablebase::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.max_empty(), 2);
+        assert_eq!(loaded.levels, tablebase.levels);
+    }
+
+    #[test]
+    fn test_too_many_empty() {
+        assert!(matches!(Tablebase::generate(5, 1, &None), Err(TbError::TooManyEmpty(5))));
+    }
+}
@@ -0,0 +1,228 @@
+//! Numbering of tablebase positions.
+//!
+//! Positions are stored from the point of view of the side to move, so the
+//! color swap costs nothing. A board symmetry then brings the mover's
+//! queen into the a1-d1-d4 triangle. Within a level (a fixed number of
+//! empty squares) the index is
+//!
+//! `(triangle square * 63 + other queen) * C(62, empty) + rank of the empty set`
+//!
+//! where the other queen and the empty squares are counted among the
+//! squares the queens before them leave free.
+
+use crate::qd::state::GameState;
+use crate::qd::symmetry::{Transform, TRANSFORMS};
+
+/// Largest level a tablebase may hold. One more level would need 4 GB.
+pub const MAX_EMPTY_LIMIT: u8 = 4;
+
+const TRIANGLE: [u8; 10] = [0, 1, 2, 3, 9, 10, 11, 18, 19, 27];
+
+const fn triangle_index() -> [u8; 64] {
+    let mut res = [u8::MAX; 64];
+    let mut i = 0;
+    while i < TRIANGLE.len() {
+        res[TRIANGLE[i] as usize] = i as u8;
+        i += 1;
+    }
+    res
+}
+
+const TRIANGLE_INDEX: [u8; 64] = triangle_index();
+
+const fn binomials() -> [[u64; 8]; 64] {
+    let mut res = [[0u64; 8]; 64];
+    let mut n = 0;
+    while n < 64 {
+        res[n][0] = 1;
+        let mut k = 1;
+        while k < 8 && k <= n {
+            res[n][k] = res[n - 1][k - 1] + res[n - 1][k];
+            k += 1;
+        }
+        n += 1;
+    }
+    res
+}
+
+const BINOMIALS: [[u64; 8]; 64] = binomials();
+
+pub fn binomial(n: u8, k: u8) -> u64 {
+    BINOMIALS[n as usize][k as usize]
+}
+
+/// Number of entries in the level with `empty` empty squares.
+pub fn level_size(empty: u8) -> u64 {
+    TRIANGLE.len() as u64 * 63 * binomial(62, empty)
+}
+
+/// A tablebase position: the queen of the side to move, the other queen,
+/// and the empty squares. Every other square is blocked.
+#[derive(PartialEq)]
+#[derive(Debug, Eq)]
+#[derive(Copy, Clone)]
+pub struct TbPosition {
+    pub mover: u8,
+    pub other: u8,
+    pub empty: u64,
+}
+
+impl TbPosition {
+    /// `None` once the queens share a square.
+    pub fn of(state: &GameState) -> Option<Self> {
+        if state.wqueen() == state.bqueen() {
+            return None;
+        }
+        let (mover, other) = if state.is_white_turn() {
+            (state.wqueen(), state.bqueen())
+        } else {
+            (state.bqueen(), state.wqueen())
+        };
+        let empty = !state.blocks() & !(1u64 << mover) & !(1u64 << other);
+        Some(Self { mover, other, empty })
+    }
+
+    pub fn blocks(&self) -> u64 {
+        !self.empty & !(1u64 << self.mover) & !(1u64 << self.other)
+    }
+
+    /// The position as a game state with white to move.
+    pub fn to_state(self) -> GameState {
+        GameState::new(Some(self.mover), Some(self.other), Some(self.blocks()), Some(true))
+    }
+
+    pub fn level(&self) -> u8 {
+        self.empty.count_ones() as u8
+    }
+
+    fn transformed(&self, transform: Transform) -> Self {
+        Self {
+            mover: transform.apply_square(self.mover),
+            other: transform.apply_square(self.other),
+            empty: transform.apply_bitboard(self.empty),
+        }
+    }
+
+    /// Index of the position within its level. When the mover's queen sits
+    /// on the a1-h8 diagonal two symmetries reach the triangle, and the
+    /// smaller index is used so that all images share one entry.
+    pub fn index(&self) -> u64 {
+        TRANSFORMS
+            .into_iter()
+            .map(|transform| self.transformed(transform))
+            .filter(|pos| TRIANGLE_INDEX[pos.mover as usize] != u8::MAX)
+            .map(|pos| pos.index_in_triangle())
+            .min()
+            .unwrap()
+    }
+
+    fn index_in_triangle(&self) -> u64 {
+        let tri = TRIANGLE_INDEX[self.mover as usize] as u64;
+        let other = (self.other - (self.other > self.mover) as u8) as u64;
+
+        let mut rank = 0;
+        let mut rest = self.empty;
+        let mut i = 1;
+        while rest != 0 {
+            let square = rest.trailing_zeros() as u8;
+            rest &= rest - 1;
+            let compressed = square - (square > self.mover) as u8 - (square > self.other) as u8;
+            rank += binomial(compressed, i);
+            i += 1;
+        }
+        (tri * 63 + other) * binomial(62, self.level()) + rank
+    }
+
+    /// The position numbered `index` in level `level`.
+    pub fn at(level: u8, index: u64) -> Self {
+        let count = binomial(62, level);
+        let (queens, mut rank) = (index / count, index % count);
+        let mover = TRIANGLE[(queens / 63) as usize];
+        let other = (queens % 63) as u8;
+        let other = other + (other >= mover) as u8;
+
+        let mut empty = 0u64;
+        for i in (1..=level).rev() {
+            let mut compressed = 61;
+            while binomial(compressed, i) > rank {
+                compressed -= 1;
+            }
+            rank -= binomial(compressed, i);
+            let mut square = compressed;
+            for queen in [mover.min(other), mover.max(other)] {
+                if square >= queen {
+                    square += 1;
+                }
+            }
+            empty |= 1u64 << square;
+        }
+        Self { mover, other, empty }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::rng_from_seed;
+    use rand::Rng;
+
+    fn random_position(rng: &mut impl Rng, level: u8) -> TbPosition {
+        let mover = rng.gen_range(0..64u8);
+        let other = (mover + rng.gen_range(1..64u8)) % 64;
+        let mut empty = 0u64;
+        while empty.count_ones() < level as u32 {
+            let square = rng.gen_range(0..64u8);
+            if square != mover && square != other {
+                empty |= 1u64 << square;
+            }
+        }
+        TbPosition { mover, other, empty }
+    }
+
+    #[test]
+    fn test_binomial() {
+        assert_eq!(binomial(62, 0), 1);
+        assert_eq!(binomial(62, 3), 37820);
+        assert_eq!(binomial(5, 7), 0);
+        assert_eq!(level_size(2), 630 * 1891);
+    }
+
+    #[test]
+    fn test_index_round_trip() {
+        for level in 0..=2 {
+            for index in (0..level_size(level)).step_by(97) {
+                let pos = TbPosition::at(level, index);
+                assert_eq!(pos.level(), level);
+                assert_ne!(pos.mover, pos.other);
+                assert_eq!(pos.empty & (1u64 << pos.mover | 1u64 << pos.other), 0);
+                assert_eq!(pos.index_in_triangle(), index, "{:?}", pos);
+                assert!(pos.index() <= index);
+            }
+        }
+    }
+
+    #[test]
+    fn test_index_in_range_and_symmetric() {
+        let mut rng = rng_from_seed(Some(31));
+        for _ in 0..2000 {
+            let level = rng.gen_range(0..=MAX_EMPTY_LIMIT);
+            let pos = random_position(&mut rng, level);
+            let index = pos.index();
+            assert!(index < level_size(level));
+            for transform in TRANSFORMS {
+                assert_eq!(pos.transformed(transform).index(), index);
+            }
+            let back = TbPosition::at(level, index).to_state();
+            assert_eq!(back.canonical(), pos.to_state().canonical());
+        }
+    }
+
+    #[test]
+    fn test_of_state() {
+        let state = GameState::from_notation("3B4/8/8/8/8/8/8/4W3 b").unwrap();
+        let pos = TbPosition::of(&state).unwrap();
+        assert_eq!((pos.mover, pos.other), (59, 4));
+        assert_eq!(pos.level(), 62);
+        assert_eq!(TbPosition::of(&GameState::new(Some(3), Some(3), None, Some(true))), None);
+    }
+}
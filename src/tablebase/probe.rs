@@ -0,0 +1,216 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use crate::qd::legalcomp::get_possible_legal_moves;
+use crate::qd::state::GameState;
+use crate::tablebase::index::{level_size, TbPosition, MAX_EMPTY_LIMIT};
+
+const MAGIC: &[u8; 4] = b"QDTB";
+const VERSION: u8 = 1;
+const WIN_BIT: u8 = 0x80;
+
+/// A solved position, seen from the side to move.
+#[derive(PartialEq)]
+#[derive(Debug, Eq)]
+#[derive(Copy, Clone)]
+pub struct TbEntry {
+    pub win: bool,
+    /// Plies until the game ends when the winner hurries and the loser
+    /// holds out.
+    pub distance: u8,
+}
+
+impl TbEntry {
+    pub fn from_byte(byte: u8) -> Self {
+        Self { win: byte & WIN_BIT != 0, distance: byte & !WIN_BIT }
+    }
+
+    pub fn to_byte(self) -> u8 {
+        if self.win { WIN_BIT | self.distance } else { self.distance }
+    }
+
+    /// The winning color in a position where `is_white_turn` is the side
+    /// to move.
+    pub fn winner(&self, is_white_turn: bool) -> bool {
+        self.win == is_white_turn
+    }
+
+    /// The entry one ply earlier, for the side that moved into this one.
+    pub fn parent(&self) -> Self {
+        Self { win: !self.win, distance: self.distance + 1 }
+    }
+
+    /// Whether `self` is better than `other` for the side to move: a win
+    /// beats a loss, a faster win beats a slower one and a slower loss
+    /// beats a faster one.
+    pub fn better_than(&self, other: &Self) -> bool {
+        match (self.win, other.win) {
+            (true, false) => true,
+            (false, true) => false,
+            (true, true) => self.distance < other.distance,
+            (false, false) => self.distance > other.distance,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TbError {
+    Io(String),
+    BadMagic,
+    BadVersion(u8),
+    TooManyEmpty(u8),
+    BadSize { expected: u64, found: u64 },
+}
+
+impl fmt::Display for TbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TbError::Io(err) => write!(f, "{}", err),
+            TbError::BadMagic => write!(f, "not a tablebase file"),
+            TbError::BadVersion(version) => write!(f, "unsupported tablebase version {}", version),
+            TbError::TooManyEmpty(n) =>
+                write!(f, "tablebases hold at most {} empty squares, not {}", MAX_EMPTY_LIMIT, n),
+            TbError::BadSize { expected, found } =>
+                write!(f, "tablebase file should hold {} bytes, found {}", expected, found),
+        }
+    }
+}
+
+impl std::error::Error for TbError {}
+
+/// Solved positions with up to `max_empty` empty squares, one byte each.
+#[derive(Debug, Clone)]
+pub struct Tablebase {
+    pub(crate) max_empty: u8,
+    pub(crate) levels: Vec<Vec<u8>>,
+}
+
+impl Tablebase {
+    pub fn max_empty(&self) -> u8 {
+        self.max_empty
+    }
+
+    /// Number of stored entries.
+    pub fn len(&self) -> u64 {
+        self.levels.iter().map(|level| level.len() as u64).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn entry(&self, pos: &TbPosition) -> TbEntry {
+        TbEntry::from_byte(self.levels[pos.level() as usize][pos.index() as usize])
+    }
+
+    /// The solved result of `state`, or `None` if it has too many empty
+    /// squares. A finished game counts as lost for the side to move.
+    pub fn probe(&self, state: &GameState) -> Option<TbEntry> {
+        let Some(pos) = TbPosition::of(state) else {
+            return Some(TbEntry { win: false, distance: 0 });
+        };
+        if pos.level() > self.max_empty {
+            return None;
+        }
+        Some(self.entry(&pos))
+    }
+
+    /// The best move in `state` and the result it keeps, or `None` if the
+    /// position is not covered or the game is over.
+    pub fn best_move(&self, state: &GameState) -> Option<(u8, TbEntry)> {
+        let pos = TbPosition::of(state)?;
+        if pos.level() > self.max_empty {
+            return None;
+        }
+        let mut best: Option<(u8, TbEntry)> = None;
+        let mut rest = get_possible_legal_moves(state);
+        while rest != 0 {
+            let to = rest.trailing_zeros() as u8;
+            rest &= rest - 1;
+            let entry = if to == pos.other {
+                TbEntry { win: true, distance: 1 }
+            } else {
+                let child = TbPosition { mover: pos.other, other: to, empty: pos.empty & !(1u64 << to) };
+                self.entry(&child).parent()
+            };
+            if best.is_none_or(|(_, best_entry)| entry.better_than(&best_entry)) {
+                best = Some((to, entry));
+            }
+        }
+        best
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(6 + self.len() as usize);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.push(self.max_empty);
+        for level in &self.levels {
+            bytes.extend_from_slice(level);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, TbError> {
+        if bytes.len() < 6 || &bytes[..4] != MAGIC {
+            return Err(TbError::BadMagic);
+        }
+        if bytes[4] != VERSION {
+            return Err(TbError::BadVersion(bytes[4]));
+        }
+        let max_empty = bytes[5];
+        if max_empty > MAX_EMPTY_LIMIT {
+            return Err(TbError::TooManyEmpty(max_empty));
+        }
+        let expected: u64 = 6 + (0..=max_empty).map(level_size).sum::<u64>();
+        if bytes.len() as u64 != expected {
+            return Err(TbError::BadSize { expected, found: bytes.len() as u64 });
+        }
+        let mut levels = Vec::new();
+        let mut offset = 6;
+        for level in 0..=max_empty {
+            let size = level_size(level) as usize;
+            levels.push(bytes[offset..offset + size].to_vec());
+            offset += size;
+        }
+        Ok(Self { max_empty, levels })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, TbError> {
+        let bytes = fs::read(path).map_err(|e| TbError::Io(e.to_string()))?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), TbError> {
+        fs::write(path, self.to_bytes()).map_err(|e| TbError::Io(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_bytes() {
+        for win in [false, true] {
+            for distance in 0..8 {
+                let entry = TbEntry { win, distance };
+                assert_eq!(TbEntry::from_byte(entry.to_byte()), entry);
+            }
+        }
+        let fast_win = TbEntry { win: true, distance: 1 };
+        let slow_win = TbEntry { win: true, distance: 3 };
+        let slow_loss = TbEntry { win: false, distance: 4 };
+        assert!(fast_win.better_than(&slow_win));
+        assert!(slow_win.better_than(&slow_loss));
+        assert!(slow_loss.better_than(&TbEntry { win: false, distance: 0 }));
+    }
+
+    #[test]
+    fn test_bad_files() {
+        assert!(matches!(Tablebase::from_bytes(b"QDRB\x01\x00"), Err(TbError::BadMagic)));
+        assert!(matches!(Tablebase::from_bytes(b"QDTB\x07\x00"), Err(TbError::BadVersion(7))));
+        assert!(matches!(Tablebase::from_bytes(b"QDTB\x01\x09"), Err(TbError::TooManyEmpty(9))));
+        assert!(matches!(Tablebase::from_bytes(b"QDTB\x01\x00\x00"), Err(TbError::BadSize { .. })));
+    }
+}